@@ -0,0 +1,113 @@
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+use crate::{
+    config::{Config, Workspace},
+    manifest::ManifestFile,
+    session::{self, SessionNames},
+    state::State,
+    tmux,
+};
+
+/// Workspaces at `indices` in the merged config, as passed around by the picker
+pub fn workspaces_at<'a>(config: &'a Config, indices: &[usize]) -> Result<Vec<&'a Workspace>> {
+    indices
+        .iter()
+        .map(|index| {
+            config
+                .get_ws_all()
+                .get(*index)
+                .ok_or(anyhow!("no workspace at index {}", index))
+        })
+        .collect()
+}
+
+pub fn kill(config: &Config, workspace: &Workspace) -> Result<()> {
+    let session_name = SessionNames::load(config)?.get(workspace);
+    if tmux::has_session(&session_name)? {
+        tmux::kill_session(&session_name)?;
+    }
+    Ok(())
+}
+
+/// Creates the session in the background without switching to it
+pub fn open_detached(config: &Config, workspace: &Workspace) -> Result<()> {
+    let session_name = SessionNames::load(config)?.get(workspace);
+    if !tmux::has_session(&session_name)? {
+        session::create(&session_name, workspace)?;
+    }
+    Ok(())
+}
+
+/// Adds the directory as a workspace, named after its manifest when no name is
+/// given. Returns the name it got, and the manifest when it isn't trusted yet
+/// so callers can point at `wsm allow`.
+pub fn add(path: &Path, name: Option<&str>) -> Result<(Option<String>, Option<ManifestFile>)> {
+    let manifest = ManifestFile::find(path)?;
+    let name = name
+        .or(manifest.as_ref().and_then(|m| m.manifest.name.as_deref()))
+        .map(|s| s.to_string());
+
+    Config::modify(|config| {
+        if config.has_ws(path) {
+            return Err(anyhow!("workspace already exists"));
+        }
+
+        config.add_ws(path, name.clone());
+        Ok(())
+    })?;
+
+    let state = State::load()?;
+    let untrusted = manifest.filter(|m| !m.is_trusted(&state));
+    Ok((name, untrusted))
+}
+
+pub fn remove(path: &Path) -> Result<()> {
+    Config::modify(|config| {
+        if !config.remove_ws(path) {
+            return Err(anyhow!("workspace does not exist in the user config"));
+        }
+        Ok(())
+    })?;
+    State::modify(|state| {
+        state.forget(path);
+        Ok(())
+    })
+}
+
+/// Sets or clears the workspace name, a running session follows the new
+/// session name
+pub fn rename(path: &Path, name: Option<String>) -> Result<()> {
+    let old_session = {
+        let config = Config::load()?;
+        let names = SessionNames::load(&config)?;
+        config
+            .get_ws_all()
+            .iter()
+            .find(|ws| ws.path == path)
+            .map(|ws| names.get(ws))
+    };
+
+    Config::modify(|config| {
+        if !config.rename_ws(path, name) {
+            return Err(anyhow!("workspace does not exist in the user config"));
+        }
+        Ok(())
+    })?;
+
+    let config = Config::load()?;
+    let new_session = config
+        .get_ws_all()
+        .iter()
+        .find(|ws| ws.path == path)
+        .map(|ws| SessionNames::load(&config).map(|names| names.get(ws)))
+        .transpose()?;
+
+    if let (Some(old), Some(new)) = (old_session, new_session)
+        && old != new
+        && tmux::has_session(&old)?
+    {
+        tmux::rename_session(&old, &new)?;
+    }
+    Ok(())
+}
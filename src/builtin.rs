@@ -0,0 +1,537 @@
+use anyhow::{Result, anyhow};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use std::{
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    actions,
+    config::{Config, Workspace},
+    fuzzy::{self, Match},
+    picker::{self, Picker, Selection},
+    preview,
+    session::SessionNames,
+    sort::{self, Sort},
+    state::State,
+    status::{self, Statuses},
+};
+
+/// Fuzzy finder drawn by wsm itself, for when fzf is not installed. It is
+/// drawn on stderr so `wsm select --print` output can still be captured.
+pub struct Builtin;
+
+impl Picker for Builtin {
+    fn pick(
+        &self,
+        config: &Config,
+        workspaces: &[(usize, &Workspace)],
+        query: Option<&str>,
+        multi: bool,
+    ) -> Result<Selection> {
+        let settings = config.get_picker();
+        let mut ui = Ui {
+            entries: load_entries(config, workspaces)?,
+            prompt: settings.prompt.clone().unwrap_or("> ".to_string()),
+            header: picker::header(settings, multi),
+            query: query.unwrap_or_default().to_string(),
+            matches: vec![],
+            cursor: 0,
+            scroll: 0,
+            marked: vec![],
+            multi,
+            preview: settings.show_preview.unwrap_or(false).then_some(None),
+            rename: None,
+            message: None,
+        };
+        ui.update_matches();
+
+        let _terminal = Terminal::enter()?;
+        let mut out = BufWriter::new(io::stderr());
+        loop {
+            ui.draw(&mut out)?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(selection) = ui.handle_key(key) {
+                return Ok(selection);
+            }
+        }
+    }
+}
+
+// raw mode and the alternate screen for as long as it lives, restored on
+// drop so errors and panics don't leave the terminal broken
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut err = io::stderr();
+        queue!(err, terminal::EnterAlternateScreen, cursor::Hide)?;
+        err.flush()?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut err = io::stderr();
+        let _ = queue!(err, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = err.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Entry {
+    /// config index
+    index: usize,
+    markers: String,
+    /// "name path", what the query is matched against
+    label: String,
+    session_name: String,
+    path: PathBuf,
+}
+
+fn load_entries(config: &Config, workspaces: &[(usize, &Workspace)]) -> Result<Vec<Entry>> {
+    let session_names = SessionNames::load(config)?;
+    let statuses = Statuses::load()?;
+
+    let entries = workspaces
+        .iter()
+        .map(|(index, ws)| {
+            let session_name = session_names.get(ws);
+            Entry {
+                index: *index,
+                markers: statuses.markers(&session_name, true),
+                label: format!("{} {}", ws.display_name(), ws.path.to_string_lossy())
+                    .replace(|c: char| c.is_control(), "?"),
+                session_name,
+                path: ws.path.clone(),
+            }
+        })
+        .collect();
+    Ok(entries)
+}
+
+// same order as `wsm picker list` uses for fzf reloads
+fn reload_entries() -> Result<Vec<Entry>> {
+    let config = Config::load()?;
+    let workspaces = sort::sort_workspaces(config.get_ws_all(), Sort::Frecency, &State::load()?);
+    load_entries(&config, &workspaces)
+}
+
+struct Ui {
+    entries: Vec<Entry>,
+    prompt: String,
+    header: String,
+    query: String,
+    /// (index into entries, match), best match first
+    matches: Vec<(usize, Match)>,
+    /// position in matches
+    cursor: usize,
+    /// first visible position in matches
+    scroll: usize,
+    /// config indices in the order they were marked
+    marked: Vec<usize>,
+    multi: bool,
+    /// rendered preview lines of the entry at the config index, None when hidden
+    preview: Option<Option<(usize, Vec<String>)>>,
+    /// input of the rename prompt while it is open
+    rename: Option<String>,
+    /// error of the last action, shown instead of the header
+    message: Option<String>,
+}
+
+enum Action {
+    Kill,
+    Remove,
+    Open,
+}
+
+impl Ui {
+    fn update_matches(&mut self) {
+        let mut matches: Vec<(usize, Match)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| fuzzy::score(&self.query, &entry.label).map(|m| (i, m)))
+            .collect();
+        // stable, ties keep the frecency order
+        matches.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+
+        self.matches = matches;
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    fn current(&self) -> Option<&Entry> {
+        self.matches
+            .get(self.cursor)
+            .map(|(i, _)| &self.entries[*i])
+    }
+
+    // marked entries, or the current one when none are marked
+    fn targets(&self) -> Vec<usize> {
+        match self.marked.is_empty() {
+            true => self.current().map(|e| e.index).into_iter().collect(),
+            false => self.marked.clone(),
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let last = self.matches.len() as isize - 1;
+        self.cursor = (self.cursor as isize + delta).clamp(0, last) as usize;
+    }
+
+    // returns the selection once the picker should close
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Selection> {
+        if self.rename.is_some() {
+            self.handle_rename_key(key);
+            return None;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Esc => return Some(Selection::default()),
+            KeyCode::Char('c' | 'g') if ctrl => return Some(Selection::default()),
+            KeyCode::Enter if alt => self.run(Action::Open),
+            KeyCode::Enter => {
+                return Some(Selection {
+                    indices: self.targets(),
+                    query: Some(self.query.clone()),
+                });
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p' | 'k') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n' | 'j') if ctrl => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-10),
+            KeyCode::PageDown => self.move_cursor(10),
+            KeyCode::Tab if self.multi => {
+                if let Some(index) = self.current().map(|e| e.index) {
+                    match self.marked.iter().position(|i| *i == index) {
+                        Some(pos) => {
+                            self.marked.remove(pos);
+                        }
+                        None => self.marked.push(index),
+                    }
+                }
+                self.move_cursor(1);
+            }
+            KeyCode::Char('t') if ctrl => {
+                self.preview = match self.preview {
+                    Some(_) => None,
+                    None => Some(None),
+                };
+            }
+            KeyCode::Char('x') if ctrl => self.run(Action::Kill),
+            KeyCode::Char('d') if ctrl => self.run(Action::Remove),
+            KeyCode::Char('r') if ctrl && self.current().is_some() => {
+                self.rename = Some(String::new());
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.update_matches();
+            }
+            KeyCode::Char('w') if ctrl => {
+                let trimmed = self.query.trim_end().len();
+                let start = self.query[..trimmed]
+                    .rfind(|c: char| c.is_whitespace() || c == '/')
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                self.query.truncate(start);
+                self.update_matches();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            KeyCode::Char(c) if !ctrl && !alt => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_rename_key(&mut self, key: KeyEvent) {
+        let Some(input) = self.rename.as_mut() else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => self.rename = None,
+            KeyCode::Char('c' | 'g') if ctrl => self.rename = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char('u') if ctrl => input.clear(),
+            KeyCode::Char(c) if !ctrl => input.push(c),
+            KeyCode::Enter => {
+                let name = input.trim().to_string();
+                self.rename = None;
+                let Some(path) = self.current().map(|e| e.path.clone()) else {
+                    return;
+                };
+                let result = actions::rename(&path, (!name.is_empty()).then_some(name));
+                self.finish_action(result);
+            }
+            _ => {}
+        }
+    }
+
+    fn run(&mut self, action: Action) {
+        let targets = self.targets();
+        let result = (|| {
+            let config = Config::load()?;
+            for index in targets {
+                let workspace = config
+                    .get_ws_all()
+                    .get(index)
+                    .ok_or(anyhow!("no workspace at index {}", index))?;
+                match action {
+                    Action::Kill => actions::kill(&config, workspace)?,
+                    Action::Remove => actions::remove(&workspace.path)?,
+                    Action::Open => actions::open_detached(&config, workspace)?,
+                }
+            }
+            Ok(())
+        })();
+        self.finish_action(result);
+    }
+
+    // reloads the list in place, like the reload bindings of fzf
+    fn finish_action(&mut self, result: Result<()>) {
+        self.message = result.err().map(|e| format!("{:#}", e));
+        match reload_entries() {
+            Ok(entries) => self.entries = entries,
+            Err(e) => self.message = Some(format!("{:#}", e)),
+        }
+
+        let cursor = self.cursor;
+        self.marked.clear();
+        self.update_matches();
+        self.cursor = cursor.min(self.matches.len().saturating_sub(1));
+        if self.preview.is_some() {
+            self.preview = Some(None);
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let rows = height.saturating_sub(2);
+
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if rows > 0 && self.cursor >= self.scroll + rows {
+            self.scroll = self.cursor + 1 - rows;
+        }
+
+        let list_width = match self.preview {
+            Some(_) => width / 2,
+            None => width,
+        };
+
+        queue!(out, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All))?;
+
+        // prompt line, the block is the cursor
+        let prompt = match (&self.rename, self.current()) {
+            (Some(input), Some(entry)) => format!(
+                "rename {} (empty to clear): {}",
+                entry.label.split(' ').next().unwrap_or_default(),
+                input
+            ),
+            _ => format!("{}{}", self.prompt, self.query),
+        };
+        queue!(
+            out,
+            Print(truncate(&prompt, list_width.saturating_sub(1))),
+            SetAttribute(Attribute::Reverse),
+            Print(" "),
+            SetAttribute(Attribute::Reset),
+        )?;
+
+        let info = match &self.message {
+            Some(message) => message.clone(),
+            None => {
+                let mut info = format!("  {}/{}", self.matches.len(), self.entries.len());
+                if !self.marked.is_empty() {
+                    info.push_str(&format!(" ({})", self.marked.len()));
+                }
+                info.push_str("  ");
+                info.push_str(&self.header);
+                info
+            }
+        };
+        let color = match self.message {
+            Some(_) => Color::Red,
+            None => Color::DarkGrey,
+        };
+        queue!(
+            out,
+            cursor::MoveTo(0, 1),
+            SetForegroundColor(color),
+            Print(truncate(&info, list_width)),
+            ResetColor,
+        )?;
+
+        for (row, (i, m)) in self.matches.iter().skip(self.scroll).take(rows).enumerate() {
+            let entry = &self.entries[*i];
+            let position = self.scroll + row;
+            queue!(out, cursor::MoveTo(0, (row + 2) as u16))?;
+            draw_entry(
+                out,
+                entry,
+                m,
+                position == self.cursor,
+                self.marked.contains(&entry.index),
+                list_width.saturating_sub(1),
+            )?;
+        }
+
+        if self.preview.is_some() {
+            self.draw_preview(out, list_width, width - list_width, height)?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    fn draw_preview(
+        &mut self,
+        out: &mut impl Write,
+        left: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        let Some((index, session_name, path)) = self
+            .current()
+            .map(|e| (e.index, e.session_name.clone(), e.path.clone()))
+        else {
+            return Ok(());
+        };
+
+        // rendering calls tmux, only redo it when the entry changes
+        let cached = matches!(&self.preview, Some(Some((i, _))) if *i == index);
+        if !cached {
+            let lines = match preview::render(&session_name, &path) {
+                Ok(text) => text.lines().map(|l| l.to_string()).collect(),
+                Err(e) => vec![format!("{:#}", e)],
+            };
+            self.preview = Some(Some((index, lines)));
+        }
+        let Some(Some((_, lines))) = &self.preview else {
+            return Ok(());
+        };
+
+        for row in 0..height {
+            queue!(
+                out,
+                cursor::MoveTo(left as u16, row as u16),
+                SetForegroundColor(Color::DarkGrey),
+                Print("│ "),
+                ResetColor,
+            )?;
+            if let Some(line) = lines.get(row) {
+                queue!(
+                    out,
+                    Print(truncate_ansi(line, width.saturating_sub(2))),
+                    SetAttribute(Attribute::Reset),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn draw_entry(
+    out: &mut impl Write,
+    entry: &Entry,
+    m: &Match,
+    current: bool,
+    marked: bool,
+    width: usize,
+) -> Result<()> {
+    queue!(
+        out,
+        Print(if current { ">" } else { " " }),
+        Print(if marked { "*" } else { " " }),
+        Print(&entry.markers),
+        Print(" "),
+    )?;
+    if current {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+
+    let available = width.saturating_sub(status::WIDTH + 3);
+    for (i, c) in entry.label.chars().take(available).enumerate() {
+        match m.positions.contains(&i) {
+            true => queue!(
+                out,
+                SetForegroundColor(Color::Green),
+                Print(c),
+                SetForegroundColor(Color::Reset)
+            )?,
+            false => queue!(out, Print(c))?,
+        }
+    }
+
+    queue!(out, SetAttribute(Attribute::Reset))?;
+    Ok(())
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+// cuts after `width` visible chars, escape sequences are copied but not counted
+fn truncate_ansi(line: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut visible = 0;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            out.push(c);
+            // CSI sequences end with a char in @..~
+            if let Some(next) = chars.next() {
+                out.push(next);
+                if next == '[' {
+                    for c in chars.by_ref() {
+                        out.push(c);
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+            }
+            continue;
+        }
+
+        if c == '\t' || c.is_control() {
+            continue;
+        }
+        if visible == width {
+            break;
+        }
+        out.push(c);
+        visible += 1;
+    }
+    out
+}
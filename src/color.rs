@@ -0,0 +1,10 @@
+//! ANSI escape codes for the picker list and the preview
+
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const RED: &str = "\x1b[31m";
+pub const DIM: &str = "\x1b[2m";
+pub const BOLD_GREEN: &str = "\x1b[1;32m";
+pub const BOLD_BLUE: &str = "\x1b[1;34m";
+pub const BOLD_CYAN: &str = "\x1b[1;36m";
+pub const RESET: &str = "\x1b[0m";
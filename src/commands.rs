@@ -1,4 +1,9 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::Result;
 
@@ -20,10 +25,20 @@ pub enum ParseError {
         path: Vec<&'static str>,
         name: String,
     },
+    InvalidArgValue {
+        path: Vec<&'static str>,
+        name: String,
+        value: String,
+        expected: String,
+    },
     MissingValue {
         path: Vec<&'static str>,
         name: String,
     },
+    UnexpectedValue {
+        path: Vec<&'static str>,
+        value: String,
+    },
     HelpRequested {
         path: Vec<&'static str>,
     },
@@ -54,14 +69,27 @@ impl Display for ParseError {
                     path.join(" ")
                 )
             }
-            ParseError::MissingValue { path, name } => {
+            ParseError::InvalidArgValue {
+                path,
+                name,
+                value,
+                expected,
+            } => {
                 write!(
                     f,
-                    "Missing value for command '{}' at '{}'",
+                    "Invalid value '{}' for argument '{}' at '{}', expected {}",
+                    value,
                     name,
-                    path.join(" ")
+                    path.join(" "),
+                    expected
                 )
             }
+            ParseError::MissingValue { path, name } => {
+                write!(f, "Missing <{}> at '{}'", name, path.join(" "))
+            }
+            ParseError::UnexpectedValue { path, value } => {
+                write!(f, "Unexpected argument '{}' at '{}'", value, path.join(" "))
+            }
             ParseError::HelpRequested { path } => {
                 write!(f, "Help requested at '{}'", path.join(" "))
             }
@@ -74,13 +102,45 @@ pub struct CommandDef {
     name: &'static str,
     description: &'static str,
     args: Vec<ArgDef>,
+    positionals: Vec<PositionalDef>,
     subcommands: Vec<CommandDef>,
+    hidden: bool,
+    complete_positionals: bool,
+    examples: Vec<(&'static str, &'static str)>,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum ArgType {
     Flag,
     Value,
+    /// Path that must exist, made absolute
+    Path,
+    /// Directory that must exist, made absolute
+    Dir,
+    /// Non-negative integer
+    Int,
+    /// One of the given words, parsed to its index
+    Choice(&'static [&'static str]),
+    /// Number of seconds, or a number followed by s, m, h or d
+    Duration,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Arity {
+    Required,
+    Optional,
+    /// Takes the remaining values, at least one
+    OneOrMore,
+    /// Takes the remaining values, possibly none
+    ZeroOrMore,
+}
+
+#[derive(Debug)]
+pub struct PositionalDef {
+    name: &'static str,
+    description: &'static str,
+    arg_type: ArgType,
+    arity: Arity,
 }
 
 #[derive(Debug)]
@@ -89,15 +149,106 @@ pub struct ArgDef {
     long: &'static str,
     description: &'static str,
     arg_type: ArgType,
+    global: bool,
 }
 
 impl CommandDef {
     pub fn new(name: &'static str, description: &'static str) -> Self {
         CommandDef {
-            name: name,
-            description: description,
+            name,
+            description,
             args: vec![],
+            positionals: vec![],
             subcommands: vec![],
+            hidden: false,
+            complete_positionals: false,
+            examples: vec![],
+        }
+    }
+
+    /// Leaves the command out of help, for commands wsm calls itself
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Positionals are completed by the hidden `wsm complete <command>` callback
+    pub fn complete_positionals(mut self) -> Self {
+        self.complete_positionals = true;
+        self
+    }
+
+    /// Example invocation with what it does, shown in the man page
+    pub fn add_example(mut self, example: &'static str, description: &'static str) -> Self {
+        self.examples.push((example, description));
+        self
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        self.description
+    }
+
+    pub fn get_subcommands(&self) -> impl Iterator<Item = &CommandDef> {
+        self.subcommands.iter().filter(|s| !s.hidden)
+    }
+
+    pub fn has_completed_positionals(&self) -> bool {
+        self.complete_positionals
+    }
+
+    pub fn get_examples(&self) -> &[(&'static str, &'static str)] {
+        &self.examples
+    }
+
+    /// One line synopsis of the command at path, `args` are the arguments
+    /// it accepts including inherited globals
+    pub fn get_usage(&self, path: &[&str], args: &[&ArgDef]) -> String {
+        let mut usage = path.join(" ");
+        for arg in args {
+            match arg.arg_type {
+                ArgType::Flag => usage.push_str(&format!(" [-{}|--{}]", arg.short, arg.long)),
+                _ => usage.push_str(&format!(" [-{} <{}>]", arg.short, arg.long)),
+            }
+        }
+        for positional in &self.positionals {
+            usage.push(' ');
+            usage.push_str(&positional.get_usage());
+        }
+        if self.get_subcommands().next().is_some() {
+            usage.push_str(" <command>");
+        }
+        usage
+    }
+
+    pub fn get_positionals(&self) -> &[PositionalDef] {
+        &self.positionals
+    }
+
+    /// Every command that is not hidden with its path and its own and
+    /// inherited arguments, parents before children
+    pub fn walk(&self) -> Vec<(Vec<&'static str>, &CommandDef, Vec<&ArgDef>)> {
+        let mut out = vec![];
+        self.walk_intrnal(vec![], vec![], &mut out);
+        out
+    }
+
+    fn walk_intrnal<'a>(
+        &'a self,
+        mut path: Vec<&'static str>,
+        mut globals: Vec<&'a ArgDef>,
+        out: &mut Vec<(Vec<&'static str>, &'a CommandDef, Vec<&'a ArgDef>)>,
+    ) {
+        path.push(self.name);
+        let args = self.args.iter().chain(globals.iter().copied()).collect();
+        out.push((path.clone(), self, args));
+
+        globals.extend(self.args.iter().filter(|a| a.global));
+        for sub in self.get_subcommands() {
+            sub.walk_intrnal(path.clone(), globals.clone(), out);
         }
     }
 
@@ -109,15 +260,58 @@ impl CommandDef {
         description: &'static str,
     ) -> Self {
         let arg = ArgDef {
-            short: short,
-            long: long,
-            description: description,
+            short,
+            long,
+            description,
             arg_type,
+            global: false,
         };
 
         self.args.push(arg);
 
-        return self;
+        self
+    }
+
+    /// Same as `add_arg`, but the argument is also accepted by every subcommand
+    pub fn add_global_arg(
+        mut self,
+        short: &'static str,
+        long: &'static str,
+        arg_type: ArgType,
+        description: &'static str,
+    ) -> Self {
+        let arg = ArgDef {
+            short,
+            long,
+            description,
+            arg_type,
+            global: true,
+        };
+
+        self.args.push(arg);
+
+        self
+    }
+
+    /// Positionals are filled in the order they are added, so optional ones
+    /// go after the required ones and only the last one can take many values
+    pub fn add_positional(
+        mut self,
+        name: &'static str,
+        arg_type: ArgType,
+        arity: Arity,
+        description: &'static str,
+    ) -> Self {
+        let positional = PositionalDef {
+            name,
+            description,
+            arg_type,
+            arity,
+        };
+
+        self.positionals.push(positional);
+
+        self
     }
 
     pub fn add_subcommand(mut self, subcommand: CommandDef) -> Self {
@@ -130,23 +324,41 @@ impl CommandDef {
             return format!("Unknown command: {}", path.join(" "));
         };
 
+        let globals = self.get_globals_from_path(path);
+        let args: Vec<&ArgDef> = command.args.iter().chain(globals).collect();
         let mut help_text = format!("Command: {}\n{}\n", command.name, command.description);
+        help_text.push_str(&format!("Usage: {}\n", command.get_usage(path, &args)));
+
+        if !command.positionals.is_empty() {
+            help_text.push_str("Positionals:\n");
+        }
+        for positional in &command.positionals {
+            help_text.push_str(&format!(
+                "  {}: {} ({})\n",
+                positional.name,
+                positional.description,
+                positional.arg_type.get_name()
+            ));
+        }
+
         help_text.push_str("Arguments:\n");
-        for arg in &command.args {
+        for arg in args {
+            let negation = match arg.get_negation() {
+                Some(negation) => format!(", --{}", negation),
+                None => String::new(),
+            };
             help_text.push_str(&format!(
-                "  -{}, --{}: {} ({})\n",
+                "  -{}, --{}{}: {} ({})\n",
                 arg.short,
                 arg.long,
+                negation,
                 arg.description,
-                match arg.arg_type {
-                    ArgType::Flag => "flag",
-                    ArgType::Value => "value",
-                }
+                arg.arg_type.get_name()
             ));
         }
 
         help_text.push_str("\nSubcommands:\n");
-        for sub in &command.subcommands {
+        for sub in command.subcommands.iter().filter(|s| !s.hidden) {
             help_text.push_str(&format!("  {}: {}\n", sub.name, sub.description));
         }
 
@@ -163,29 +375,44 @@ impl CommandDef {
             return Some(self);
         }
 
-        if len > 1 {
-            if let Some(command) = self.find_subcommand(&path[1]) {
-                return command.get_command_from_path(&path[1..]);
-            }
+        if len > 1
+            && let Some(command) = self.find_subcommand(path[1])
+        {
+            return command.get_command_from_path(&path[1..]);
         }
 
         None
     }
 
+    // global args inherited from the ancestors of the command at path
+    fn get_globals_from_path(&self, path: &[&str]) -> Vec<&ArgDef> {
+        let mut globals = vec![];
+        let mut command = self;
+        for name in path.iter().skip(1) {
+            globals.extend(command.args.iter().filter(|a| a.global));
+            match command.find_subcommand(name) {
+                Some(sub) => command = sub,
+                None => break,
+            }
+        }
+        globals
+    }
+
     pub fn parse(&self, args: std::env::Args) -> Result<Command, ParseError> {
         let args_vec: Vec<String> = args.collect();
         let tokens = tokenize(&args_vec);
-        self.parse_intrnal(&tokens, vec![])
+        self.parse_intrnal(&tokens, vec![], vec![], HashMap::new())
     }
 
-    fn parse_intrnal(
-        &self,
+    fn parse_intrnal<'a>(
+        &'a self,
         tokens: &[Token],
         mut path: Vec<&'static str>,
+        mut globals: Vec<&'a ArgDef>,
+        mut args: HashMap<&'static str, Vec<Arg>>,
     ) -> Result<Command, ParseError> {
-        let mut args = HashMap::new();
         let mut positional = vec![];
-        let mut can_be_subcommand = self.subcommands.len() > 0;
+        let mut can_be_subcommand = !self.subcommands.is_empty();
 
         path.push(self.name);
 
@@ -218,57 +445,98 @@ impl CommandDef {
             if can_be_subcommand && let Token::Word(name) = arg {
                 let sub_def = self.find_subcommand(name);
                 if let Some(def) = sub_def {
-                    let command = def.parse_intrnal(&tokens[i..], path)?;
+                    globals.extend(self.args.iter().filter(|a| a.global));
+                    let command = def.parse_intrnal(&tokens[i..], path, globals, args)?;
                     return Ok(command);
                 }
             }
 
-            can_be_subcommand = false;
-
-            if let Token::Long(name) | Token::Short(name) = arg {
+            if let Token::Long(name) = arg {
                 if name == "help" || name == "h" {
                     return Err(ParseError::HelpRequested { path });
                 }
-                let Some(arg_def) = self.find_arg(name) else {
-                    return Err(ParseError::UnknownArg {
-                        path,
-                        name: name.to_string(),
-                    });
+                let Some(arg_def) = self.find_arg(name, &globals) else {
+                    // --no-<flag> drops the flag given before it
+                    let negated = name
+                        .strip_prefix("no-")
+                        .and_then(|name| self.find_arg(name, &globals))
+                        .filter(|def| def.arg_type == ArgType::Flag);
+                    let Some(arg_def) = negated else {
+                        return Err(ParseError::UnknownArg {
+                            path,
+                            name: name.to_string(),
+                        });
+                    };
+                    args.remove(arg_def.long);
+                    i += 1;
+                    continue;
                 };
 
                 let parsed_arg = match arg_def.arg_type {
                     ArgType::Flag => Arg::Flag,
-                    ArgType::Value => {
-                        let val_i = i + 1;
-                        if val_i >= tokens.len() {
-                            return Err(ParseError::MissingArgValue {
-                                path,
-                                name: name.to_string(),
-                            });
-                        }
-
-                        let next = &tokens[i + 1];
-
-                        let Token::Word(val) = next else {
+                    _ => {
+                        let Some(Token::Word(val)) = tokens.get(i + 1) else {
                             return Err(ParseError::MissingArgValue {
                                 path,
                                 name: name.to_string(),
                             });
                         };
-
                         i += 1;
-                        Arg::Value(val.to_string())
+                        parse_value(arg_def, name, val, &path)?
                     }
                 };
 
-                args.insert(arg_def.long, parsed_arg);
+                args.entry(arg_def.long).or_default().push(parsed_arg);
                 i += 1;
 
                 continue;
             }
 
+            // -pm is -p -m, the last one can take a value: -pn value, -pnvalue
+            // or -pn=value
+            if let Token::Short(shorts) = arg {
+                for (pos, c) in shorts.char_indices() {
+                    let name = c.to_string();
+                    if name == "h" {
+                        return Err(ParseError::HelpRequested { path });
+                    }
+                    let Some(arg_def) = self.find_arg(&name, &globals) else {
+                        return Err(ParseError::UnknownArg { path, name });
+                    };
+
+                    let rest = &shorts[pos + c.len_utf8()..];
+                    if arg_def.arg_type == ArgType::Flag {
+                        if rest.starts_with('=') {
+                            return Err(ParseError::UnexpectedArgValue { path, name });
+                        }
+                        args.entry(arg_def.long).or_default().push(Arg::Flag);
+                        continue;
+                    }
+
+                    // -n= is an empty value like --name=, only a bare -n takes
+                    // the next word
+                    let val = match rest.strip_prefix('=') {
+                        Some(attached) => attached,
+                        None if !rest.is_empty() => rest,
+                        None => {
+                            let Some(Token::Word(val)) = tokens.get(i + 1) else {
+                                return Err(ParseError::MissingArgValue { path, name });
+                            };
+                            i += 1;
+                            val.as_str()
+                        }
+                    };
+                    let parsed_arg = parse_value(arg_def, &name, val, &path)?;
+                    args.entry(arg_def.long).or_default().push(parsed_arg);
+                    break;
+                }
+
+                i += 1;
+                continue;
+            }
+
             if let Token::LongWithValue(name, val) = arg {
-                let Some(arg_def) = self.find_arg(&name) else {
+                let Some(arg_def) = self.find_arg(name, &globals) else {
                     return Err(ParseError::UnknownArg {
                         path,
                         name: name.to_string(),
@@ -282,34 +550,222 @@ impl CommandDef {
                     });
                 }
 
-                let parsed_arg = Arg::Value(val.to_string());
-
-                args.insert(arg_def.long, parsed_arg);
+                let parsed_arg = parse_value(arg_def, name, val, &path)?;
+                args.entry(arg_def.long).or_default().push(parsed_arg);
             }
 
             if let Token::Word(word) = arg {
+                if can_be_subcommand && self.positionals.is_empty() {
+                    return Err(ParseError::UnknownCommand {
+                        path,
+                        name: word.to_string(),
+                    });
+                }
+                can_be_subcommand = false;
                 positional.push(word.to_string());
             }
 
             i += 1;
         }
 
+        let positionals = self.parse_positionals(positional, &path)?;
+
         Ok(Command {
             path,
             args,
-            positional,
+            positionals,
         })
     }
 
+    // assigns the positional words to the declared positionals in order
+    fn parse_positionals(
+        &self,
+        words: Vec<String>,
+        path: &[&'static str],
+    ) -> Result<HashMap<&'static str, Vec<Arg>>, ParseError> {
+        let mut words = words.into_iter();
+        let mut positionals = HashMap::new();
+
+        for def in &self.positionals {
+            let count = match def.arity {
+                Arity::Required | Arity::Optional => 1,
+                Arity::OneOrMore | Arity::ZeroOrMore => usize::MAX,
+            };
+
+            let mut values = vec![];
+            for word in words.by_ref().take(count) {
+                let Some(value) = def.arg_type.parse(&word) else {
+                    return Err(ParseError::InvalidArgValue {
+                        path: path.to_vec(),
+                        name: def.name.to_string(),
+                        value: word,
+                        expected: def.arg_type.get_expected(),
+                    });
+                };
+                values.push(value);
+            }
+
+            if values.is_empty() && matches!(def.arity, Arity::Required | Arity::OneOrMore) {
+                return Err(ParseError::MissingValue {
+                    path: path.to_vec(),
+                    name: def.name.to_string(),
+                });
+            }
+            positionals.insert(def.name, values);
+        }
+
+        if let Some(value) = words.next() {
+            return Err(ParseError::UnexpectedValue {
+                path: path.to_vec(),
+                value,
+            });
+        }
+
+        Ok(positionals)
+    }
+
     fn find_subcommand(&self, name: &str) -> Option<&CommandDef> {
         self.subcommands.iter().find(|s| s.name == name)
     }
 
-    fn find_arg(&self, name: &str) -> Option<&ArgDef> {
-        self.args.iter().find(|s| s.long == name || s.short == name)
+    fn find_arg<'a>(&'a self, name: &str, globals: &[&'a ArgDef]) -> Option<&'a ArgDef> {
+        self.args
+            .iter()
+            .chain(globals.iter().copied())
+            .find(|s| s.long == name || s.short == name)
+    }
+}
+
+impl PositionalDef {
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        self.description
+    }
+
+    // <path>, [path], <indices>... or [query]...
+    pub fn get_usage(&self) -> String {
+        match self.arity {
+            Arity::Required => format!("<{}>", self.name),
+            Arity::Optional => format!("[{}]", self.name),
+            Arity::OneOrMore => format!("<{}>...", self.name),
+            Arity::ZeroOrMore => format!("[{}]...", self.name),
+        }
+    }
+}
+
+impl ArgDef {
+    pub fn get_short(&self) -> &'static str {
+        self.short
+    }
+
+    pub fn get_long(&self) -> &'static str {
+        self.long
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        self.description
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+
+    pub fn takes_value(&self) -> bool {
+        self.arg_type != ArgType::Flag
+    }
+
+    /// `no-<long>` for flags, --no-<long> drops the flag given before it
+    pub fn get_negation(&self) -> Option<String> {
+        (self.arg_type == ArgType::Flag).then(|| format!("no-{}", self.long))
+    }
+
+    pub fn get_choices(&self) -> Option<&'static [&'static str]> {
+        match self.arg_type {
+            ArgType::Choice(choices) => Some(choices),
+            _ => None,
+        }
+    }
+}
+
+impl ArgType {
+    // shown after the argument in help
+    fn get_name(&self) -> String {
+        match self {
+            ArgType::Flag => String::from("flag"),
+            ArgType::Value => String::from("value"),
+            ArgType::Path => String::from("path"),
+            ArgType::Dir => String::from("directory"),
+            ArgType::Int => String::from("number"),
+            ArgType::Choice(choices) => choices.join("|"),
+            ArgType::Duration => String::from("duration"),
+        }
+    }
+
+    fn get_expected(&self) -> String {
+        match self {
+            ArgType::Flag => String::from("no value"),
+            ArgType::Value => String::from("a value"),
+            ArgType::Path => String::from("an existing path"),
+            ArgType::Dir => String::from("an existing directory"),
+            ArgType::Int => String::from("a non-negative number"),
+            ArgType::Choice(choices) => format!("one of {}", choices.join(", ")),
+            ArgType::Duration => String::from("a duration like 90, 30s, 5m, 2h or 1d"),
+        }
+    }
+
+    /// Checks the value and converts it, `None` when it doesn't fit the type
+    fn parse(&self, val: &str) -> Option<Arg> {
+        match self {
+            ArgType::Flag => None,
+            ArgType::Value => Some(Arg::Value(val.to_string())),
+            ArgType::Path => PathBuf::from(val).canonicalize().ok().map(Arg::Path),
+            ArgType::Dir => PathBuf::from(val)
+                .canonicalize()
+                .ok()
+                .filter(|path| path.is_dir())
+                .map(Arg::Path),
+            ArgType::Int => val.parse::<usize>().ok().map(Arg::Int),
+            ArgType::Choice(choices) => choices.iter().position(|c| *c == val).map(Arg::Choice),
+            ArgType::Duration => parse_duration(val).map(Arg::Duration),
+        }
     }
 }
 
+// typed value of an argument, `name` is the argument as it was given
+fn parse_value(
+    arg_def: &ArgDef,
+    name: &str,
+    val: &str,
+    path: &[&'static str],
+) -> Result<Arg, ParseError> {
+    arg_def
+        .arg_type
+        .parse(val)
+        .ok_or_else(|| ParseError::InvalidArgValue {
+            path: path.to_vec(),
+            name: name.to_string(),
+            value: val.to_string(),
+            expected: arg_def.arg_type.get_expected(),
+        })
+}
+
+fn parse_duration(val: &str) -> Option<Duration> {
+    let split = val.find(|c: char| !c.is_ascii_digit()).unwrap_or(val.len());
+    let (number, unit) = val.split_at(split);
+    let number = number.parse::<u64>().ok()?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
 #[derive(Debug)]
 enum Token {
     Short(String),
@@ -343,7 +799,10 @@ fn tokenize(args: &[String]) -> Vec<Token> {
             continue;
         }
 
-        if let Some(rest) = arg.strip_prefix("-") {
+        // a lone - is a word, commonly meaning stdin
+        if arg != "-"
+            && let Some(rest) = arg.strip_prefix("-")
+        {
             out.push(Token::Short(rest.to_string()));
             continue;
         }
@@ -357,14 +816,56 @@ fn tokenize(args: &[String]) -> Vec<Token> {
 #[derive(Debug)]
 pub struct Command {
     path: Vec<&'static str>,
-    args: HashMap<&'static str, Arg>,
-    positional: Vec<String>,
+    args: HashMap<&'static str, Vec<Arg>>,
+    positionals: HashMap<&'static str, Vec<Arg>>,
 }
 
 #[derive(Debug)]
 pub enum Arg {
     Value(String),
     Flag,
+    Path(PathBuf),
+    Int(usize),
+    Duration(Duration),
+    /// index of the word in the choices of the argument type
+    Choice(usize),
+}
+
+impl Arg {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Arg::Value(val) => Some(val.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_path(&self) -> Option<&Path> {
+        match self {
+            Arg::Path(path) => Some(path.as_path()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<usize> {
+        match self {
+            Arg::Int(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn as_duration(&self) -> Option<Duration> {
+        match self {
+            Arg::Duration(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn as_choice(&self) -> Option<usize> {
+        match self {
+            Arg::Choice(index) => Some(*index),
+            _ => None,
+        }
+    }
 }
 
 impl Command {
@@ -372,18 +873,218 @@ impl Command {
         &self.path
     }
 
+    /// Last occurrence of the argument, later ones override earlier ones
     pub fn get_arg(&self, long: &str) -> Option<&Arg> {
-        self.args.get(long)
+        self.get_args(long).last()
+    }
+
+    /// Every occurrence of the argument, in the order they were given
+    pub fn get_args(&self, long: &str) -> &[Arg] {
+        self.args.get(long).map_or(&[], |values| values.as_slice())
     }
 
     pub fn get_arg_value(&self, long: &str) -> Option<&str> {
-        self.args.get(long).and_then(|arg| match arg {
-            Arg::Value(val) => Some(val.as_str()),
-            Arg::Flag => None,
-        })
+        self.get_arg(long).and_then(Arg::as_str)
+    }
+
+    pub fn get_arg_path(&self, long: &str) -> Option<&Path> {
+        self.get_arg(long).and_then(Arg::as_path)
+    }
+
+    pub fn get_arg_int(&self, long: &str) -> Option<usize> {
+        self.get_arg(long).and_then(Arg::as_int)
+    }
+
+    pub fn get_arg_duration(&self, long: &str) -> Option<Duration> {
+        self.get_arg(long).and_then(Arg::as_duration)
+    }
+
+    pub fn get_arg_choice(&self, long: &str) -> Option<usize> {
+        self.get_arg(long).and_then(Arg::as_choice)
+    }
+
+    /// First value of the positional, `None` when an optional one was left out
+    pub fn get_positional(&self, name: &str) -> Option<&Arg> {
+        self.get_positionals(name).first()
+    }
+
+    /// All values of the positional, in the order they were given
+    pub fn get_positionals(&self, name: &str) -> &[Arg] {
+        self.positionals
+            .get(name)
+            .map_or(&[], |values| values.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def() -> CommandDef {
+        CommandDef::new("wsm", "Test command")
+            .add_arg("p", "print", ArgType::Flag, "Print")
+            .add_arg("m", "multi", ArgType::Flag, "Multi")
+            .add_arg("n", "name", ArgType::Value, "Name")
+            .add_arg("l", "lines", ArgType::Int, "Lines")
+            .add_arg("s", "sort", ArgType::Choice(&["name", "path"]), "Sort")
+            .add_positional("words", ArgType::Value, Arity::ZeroOrMore, "Words")
+    }
+
+    // parses the args as given after the binary name
+    fn parse(def: &CommandDef, args: &[&str]) -> Result<Command, ParseError> {
+        let args: Vec<String> = std::iter::once("wsm")
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect();
+        def.parse_intrnal(&tokenize(&args), vec![], vec![], HashMap::new())
+    }
+
+    fn words(cmd: &Command) -> Vec<&str> {
+        cmd.get_positionals("words")
+            .iter()
+            .filter_map(Arg::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn bundled_short_flags() {
+        let cmd = parse(&def(), &["-pm"]).unwrap();
+        assert!(cmd.get_arg("print").is_some());
+        assert!(cmd.get_arg("multi").is_some());
+    }
+
+    #[test]
+    fn last_bundled_short_takes_the_value() {
+        let cmd = parse(&def(), &["-pnapi"]).unwrap();
+        assert!(cmd.get_arg("print").is_some());
+        assert_eq!(cmd.get_arg_value("name"), Some("api"));
+
+        let cmd = parse(&def(), &["-pn", "api"]).unwrap();
+        assert_eq!(cmd.get_arg_value("name"), Some("api"));
+    }
+
+    #[test]
+    fn attached_short_values() {
+        let cmd = parse(&def(), &["-napi"]).unwrap();
+        assert_eq!(cmd.get_arg_value("name"), Some("api"));
+
+        let cmd = parse(&def(), &["-n=api"]).unwrap();
+        assert_eq!(cmd.get_arg_value("name"), Some("api"));
+    }
+
+    #[test]
+    fn equals_without_value_is_empty() {
+        let cmd = parse(&def(), &["-n="]).unwrap();
+        assert_eq!(cmd.get_arg_value("name"), Some(""));
+
+        let cmd = parse(&def(), &["-n=", "word"]).unwrap();
+        assert_eq!(cmd.get_arg_value("name"), Some(""));
+        assert_eq!(words(&cmd), ["word"]);
+
+        let cmd = parse(&def(), &["--name=", "word"]).unwrap();
+        assert_eq!(cmd.get_arg_value("name"), Some(""));
+        assert_eq!(words(&cmd), ["word"]);
+    }
+
+    #[test]
+    fn short_value_without_word_is_missing() {
+        let err = parse(&def(), &["-n"]).unwrap_err();
+        assert!(matches!(err, ParseError::MissingArgValue { name, .. } if name == "n"));
+    }
+
+    #[test]
+    fn flag_with_attached_value_is_rejected() {
+        let err = parse(&def(), &["-p=yes"]).unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedArgValue { name, .. } if name == "p"));
+    }
+
+    #[test]
+    fn repeated_args_keep_every_occurrence() {
+        let cmd = parse(&def(), &["-n", "a", "--name", "b", "-nc"]).unwrap();
+        let values: Vec<&str> = cmd
+            .get_args("name")
+            .iter()
+            .filter_map(Arg::as_str)
+            .collect();
+        assert_eq!(values, ["a", "b", "c"]);
+        assert_eq!(cmd.get_arg_value("name"), Some("c"));
+    }
+
+    #[test]
+    fn negation_drops_an_earlier_flag() {
+        let cmd = parse(&def(), &["-p", "--no-print"]).unwrap();
+        assert!(cmd.get_arg("print").is_none());
+
+        let cmd = parse(&def(), &["--no-print", "-p"]).unwrap();
+        assert!(cmd.get_arg("print").is_some());
+    }
+
+    #[test]
+    fn negation_of_a_value_arg_is_unknown() {
+        let err = parse(&def(), &["--no-name"]).unwrap_err();
+        assert!(matches!(err, ParseError::UnknownArg { name, .. } if name == "no-name"));
     }
 
-    pub fn get_positional_string(&self) -> String {
-        self.positional.join(" ")
+    #[test]
+    fn lone_dash_is_a_word() {
+        let cmd = parse(&def(), &["-", "-p"]).unwrap();
+        assert_eq!(words(&cmd), ["-"]);
+        assert!(cmd.get_arg("print").is_some());
+    }
+
+    #[test]
+    fn typed_values_are_checked() {
+        let cmd = parse(&def(), &["-l", "20", "-s", "path"]).unwrap();
+        assert_eq!(cmd.get_arg_int("lines"), Some(20));
+        assert_eq!(cmd.get_arg_choice("sort"), Some(1));
+
+        let err = parse(&def(), &["-l", "x"]).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidArgValue { value, .. } if value == "x"));
+
+        let err = parse(&def(), &["--sort", "size"]).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidArgValue { value, .. } if value == "size"));
+    }
+
+    #[test]
+    fn positionals_follow_their_arity() {
+        let def = CommandDef::new("wsm", "Test command")
+            .add_positional("src", ArgType::Value, Arity::Required, "Source")
+            .add_positional("dst", ArgType::Value, Arity::Optional, "Destination");
+
+        let cmd = parse(&def, &["a"]).unwrap();
+        assert_eq!(cmd.get_positional("src").and_then(Arg::as_str), Some("a"));
+        assert!(cmd.get_positional("dst").is_none());
+
+        let err = parse(&def, &[]).unwrap_err();
+        assert!(matches!(err, ParseError::MissingValue { name, .. } if name == "src"));
+
+        let err = parse(&def, &["a", "b", "c"]).unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedValue { value, .. } if value == "c"));
+    }
+
+    #[test]
+    fn one_or_more_needs_a_value() {
+        let def = CommandDef::new("wsm", "Test command").add_positional(
+            "indices",
+            ArgType::Int,
+            Arity::OneOrMore,
+            "Indices",
+        );
+
+        let cmd = parse(&def, &["1", "2"]).unwrap();
+        assert_eq!(cmd.get_positionals("indices").len(), 2);
+
+        let err = parse(&def, &[]).unwrap_err();
+        assert!(matches!(err, ParseError::MissingValue { name, .. } if name == "indices"));
+    }
+
+    #[test]
+    fn unknown_word_without_positionals_is_an_unknown_command() {
+        let def =
+            CommandDef::new("wsm", "Test command").add_subcommand(CommandDef::new("ls", "List"));
+
+        assert!(parse(&def, &["ls"]).is_ok());
+        let err = parse(&def, &["lss"]).unwrap_err();
+        assert!(matches!(err, ParseError::UnknownCommand { name, .. } if name == "lss"));
     }
 }
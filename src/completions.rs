@@ -0,0 +1,410 @@
+use anyhow::Result;
+use std::fmt::Write;
+
+use crate::{
+    commands::{ArgDef, CommandDef},
+    shell::quote,
+};
+
+type Walked<'a> = Vec<(Vec<&'static str>, &'a CommandDef, Vec<&'a ArgDef>)>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Names for `wsm completions`, in the order of `ALL`
+    pub const CHOICES: &[&str; 3] = &["bash", "zsh", "fish"];
+    pub const ALL: [Shell; 3] = [Shell::Bash, Shell::Zsh, Shell::Fish];
+}
+
+/// Completion script for bash, zsh or fish generated from the command tree.
+/// Positionals of commands marked with `complete_positionals` are completed
+/// by calling `<bin> complete <command>`, with the global value options of the
+/// command line (`--config`) passed on.
+pub fn generate(shell: Shell, def: &CommandDef) -> Result<String> {
+    let commands = def.walk();
+    match shell {
+        Shell::Bash => bash(def.get_name(), &commands),
+        Shell::Zsh => zsh(def.get_name(), &commands),
+        Shell::Fish => fish(def.get_name(), &commands),
+    }
+}
+
+// "wsm layout" + "save" for every parent and subcommand, used by the scripts
+// to find the command being completed
+fn transitions(commands: &Walked) -> Vec<String> {
+    commands
+        .iter()
+        .flat_map(|(path, cmd, _)| {
+            cmd.get_subcommands()
+                .map(move |sub| format!("{} {}", path.join(" "), sub.get_name()))
+        })
+        .collect()
+}
+
+fn options(args: &[&ArgDef]) -> Vec<String> {
+    args.iter()
+        .flat_map(|a| {
+            let negation = a.get_negation().map(|negation| format!("--{}", negation));
+            [format!("-{}", a.get_short()), format!("--{}", a.get_long())]
+                .into_iter()
+                .chain(negation)
+        })
+        .collect()
+}
+
+// shown next to --no-<long> in zsh and fish
+fn negation_description(arg: &ArgDef) -> String {
+    format!("Undo an earlier --{}", arg.get_long())
+}
+
+// "-s|--sort" patterns of the value options with their choices, options
+// without choices complete files
+fn value_options<'a>(args: &[&'a ArgDef]) -> Vec<(String, Option<&'a [&'a str]>)> {
+    args.iter()
+        .filter(|a| a.takes_value())
+        .map(|a| (options(&[*a]).join("|"), a.get_choices()))
+        .collect()
+}
+
+// `forwarded` expands to the global value options already typed
+fn callback(bin: &str, path: &[&str], forwarded: &str) -> String {
+    format!(
+        "{} complete {} {} 2>/dev/null",
+        bin,
+        path[1..].join(" "),
+        forwarded
+    )
+}
+
+// "-c|--config" pattern of the global value options of the root command, they
+// are passed on to the callback so it reads the same config
+fn forwarded_pattern(commands: &Walked) -> Option<String> {
+    let globals: Vec<&ArgDef> = commands
+        .first()?
+        .2
+        .iter()
+        .copied()
+        .filter(|a| a.is_global() && a.takes_value())
+        .collect();
+    (!globals.is_empty()).then(|| options(&globals).join("|"))
+}
+
+fn bash(bin: &str, commands: &Walked) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# bash completion for {bin}, generated by `{bin} completions bash`"
+    )?;
+    writeln!(out, "_{}() {{", bin)?;
+    writeln!(out, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(out, "    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    writeln!(out, "    local cmd={} i line forwarded=()", bin)?;
+    writeln!(out, "    for ((i = 1; i < COMP_CWORD; i++)); do")?;
+    if let Some(pattern) = forwarded_pattern(commands) {
+        writeln!(out, "        case \"${{COMP_WORDS[i]}}\" in")?;
+        writeln!(
+            out,
+            "            {}) forwarded+=(\"${{COMP_WORDS[i]}}\" \"${{COMP_WORDS[i+1]/#\\~/$HOME}}\") ;;",
+            pattern
+        )?;
+        writeln!(out, "        esac")?;
+    }
+    writeln!(out, "        case \"$cmd ${{COMP_WORDS[i]}}\" in")?;
+    let patterns: Vec<String> = transitions(commands).iter().map(quote).collect();
+    writeln!(
+        out,
+        "            {}) cmd=\"$cmd ${{COMP_WORDS[i]}}\" ;;",
+        patterns.join("|")
+    )?;
+    writeln!(out, "        esac")?;
+    writeln!(out, "    done")?;
+    writeln!(out)?;
+    writeln!(out, "    COMPREPLY=()")?;
+    writeln!(out, "    case \"$cmd\" in")?;
+
+    for (path, cmd, args) in commands {
+        writeln!(out, "        {})", quote(path.join(" ")))?;
+
+        let values = value_options(args);
+        if !values.is_empty() {
+            writeln!(out, "            case \"$prev\" in")?;
+        }
+        for (pattern, choices) in &values {
+            let words = match choices {
+                Some(choices) => format!("-W {}", quote(choices.join(" "))),
+                None => String::from("-f"),
+            };
+            writeln!(
+                out,
+                "                {}) COMPREPLY=($(compgen {} -- \"$cur\")); return ;;",
+                pattern, words
+            )?;
+        }
+        if !values.is_empty() {
+            writeln!(out, "            esac")?;
+        }
+
+        let subcommands: Vec<&str> = cmd.get_subcommands().map(|s| s.get_name()).collect();
+        writeln!(out, "            if [[ $cur == -* ]]; then")?;
+        writeln!(
+            out,
+            "                COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+            quote(options(args).join(" "))
+        )?;
+        if !subcommands.is_empty() {
+            writeln!(out, "            else")?;
+            writeln!(
+                out,
+                "                COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+                quote(subcommands.join(" "))
+            )?;
+        } else if cmd.has_completed_positionals() {
+            writeln!(out, "            else")?;
+            writeln!(out, "                while IFS= read -r line; do")?;
+            writeln!(
+                out,
+                "                    [[ $line == \"$cur\"* ]] && COMPREPLY+=(\"$(printf '%q' \"$line\")\")"
+            )?;
+            writeln!(
+                out,
+                "                done < <({})",
+                callback(bin, path, "\"${forwarded[@]}\"")
+            )?;
+        }
+        writeln!(out, "            fi")?;
+        writeln!(out, "            ;;")?;
+    }
+
+    writeln!(out, "    esac")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "complete -F _{} {}", bin, bin)?;
+    Ok(out)
+}
+
+// "name:description" for _describe, colons in the name must be escaped
+fn describe_entry(name: &str, description: &str) -> String {
+    quote(format!("{}:{}", name.replace(':', "\\:"), description))
+}
+
+fn zsh(bin: &str, commands: &Walked) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "#compdef {}", bin)?;
+    writeln!(
+        out,
+        "# zsh completion for {bin}, generated by `{bin} completions zsh`"
+    )?;
+    writeln!(out, "_{}() {{", bin)?;
+    writeln!(
+        out,
+        "    local cmd={} prev=${{words[CURRENT-1]}} i forwarded=()",
+        bin
+    )?;
+    writeln!(out, "    for ((i = 2; i < CURRENT; i++)); do")?;
+    if let Some(pattern) = forwarded_pattern(commands) {
+        writeln!(out, "        case ${{words[i]}} in")?;
+        writeln!(
+            out,
+            "            ({}) forwarded+=(\"${{words[i]}}\" \"${{words[i+1]/#\\~/$HOME}}\") ;;",
+            pattern
+        )?;
+        writeln!(out, "        esac")?;
+    }
+    writeln!(out, "        case \"$cmd ${{words[i]}}\" in")?;
+    let patterns: Vec<String> = transitions(commands).iter().map(quote).collect();
+    writeln!(
+        out,
+        "            ({}) cmd=\"$cmd ${{words[i]}}\" ;;",
+        patterns.join("|")
+    )?;
+    writeln!(out, "        esac")?;
+    writeln!(out, "    done")?;
+    writeln!(out)?;
+    writeln!(out, "    case $cmd in")?;
+
+    for (path, cmd, args) in commands {
+        writeln!(out, "        ({})", quote(path.join(" ")))?;
+
+        let values = value_options(args);
+        if !values.is_empty() {
+            writeln!(out, "            case $prev in")?;
+        }
+        for (pattern, choices) in &values {
+            let action = match choices {
+                Some(choices) => format!("compadd -- {}", choices.join(" ")),
+                None => String::from("_files"),
+            };
+            writeln!(out, "                ({}) {}; return ;;", pattern, action)?;
+        }
+        if !values.is_empty() {
+            writeln!(out, "            esac")?;
+        }
+
+        let options: Vec<String> = args
+            .iter()
+            .flat_map(|a| {
+                let negation = a.get_negation().map(|negation| {
+                    describe_entry(&format!("--{}", negation), &negation_description(a))
+                });
+                [
+                    describe_entry(&format!("-{}", a.get_short()), a.get_description()),
+                    describe_entry(&format!("--{}", a.get_long()), a.get_description()),
+                ]
+                .into_iter()
+                .chain(negation)
+            })
+            .collect();
+        writeln!(out, "            if [[ $PREFIX == -* ]]; then")?;
+        writeln!(
+            out,
+            "                local -a options=({})",
+            options.join(" ")
+        )?;
+        writeln!(out, "                _describe option options")?;
+
+        let subcommands: Vec<String> = cmd
+            .get_subcommands()
+            .map(|s| describe_entry(s.get_name(), s.get_description()))
+            .collect();
+        if !subcommands.is_empty() {
+            writeln!(out, "            else")?;
+            writeln!(
+                out,
+                "                local -a commands=({})",
+                subcommands.join(" ")
+            )?;
+            writeln!(out, "                _describe command commands")?;
+        } else if cmd.has_completed_positionals() {
+            writeln!(out, "            else")?;
+            writeln!(
+                out,
+                "                local -a items=(\"${{(@f)$({})}}\")",
+                callback(bin, path, "\"${forwarded[@]}\"")
+            )?;
+            writeln!(out, "                compadd -- \"${{items[@]}}\"")?;
+        }
+        writeln!(out, "            fi")?;
+        writeln!(out, "            ;;")?;
+    }
+
+    writeln!(out, "    esac")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "if [[ $zsh_eval_context[-1] == loadautofunc ]]; then")?;
+    writeln!(out, "    _{} \"$@\"", bin)?;
+    writeln!(out, "else")?;
+    writeln!(out, "    compdef _{} {}", bin, bin)?;
+    writeln!(out, "fi")?;
+    Ok(out)
+}
+
+// fish single quotes only treat \ and ' specially
+fn fish_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish(bin: &str, commands: &Walked) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# fish completion for {bin}, generated by `{bin} completions fish`"
+    )?;
+    writeln!(out, "function __{}_cmd", bin)?;
+    writeln!(out, "    set -l cmd {}", bin)?;
+    writeln!(out, "    for word in (commandline -opc)[2..-1]")?;
+    writeln!(out, "        switch \"$cmd $word\"")?;
+    let patterns: Vec<String> = transitions(commands)
+        .iter()
+        .map(|t| fish_quote(t))
+        .collect();
+    writeln!(out, "            case {}", patterns.join(" "))?;
+    writeln!(out, "                set cmd \"$cmd $word\"")?;
+    writeln!(out, "        end")?;
+    writeln!(out, "    end")?;
+    writeln!(out, "    echo $cmd")?;
+    writeln!(out, "end")?;
+    writeln!(out)?;
+    writeln!(out, "function __{}_forwarded", bin)?;
+    if let Some(pattern) = forwarded_pattern(commands) {
+        let patterns: Vec<String> = pattern.split('|').map(fish_quote).collect();
+        writeln!(out, "    set -l words (commandline -opc)")?;
+        writeln!(out, "    for i in (seq 2 (math (count $words) - 1))")?;
+        writeln!(out, "        switch $words[$i]")?;
+        writeln!(out, "            case {}", patterns.join(" "))?;
+        writeln!(out, "                echo $words[$i]")?;
+        writeln!(
+            out,
+            "                string replace -r '^~' $HOME -- $words[(math $i + 1)]"
+        )?;
+        writeln!(out, "        end")?;
+        writeln!(out, "    end")?;
+    }
+    writeln!(out, "end")?;
+    writeln!(out)?;
+    writeln!(out, "complete -c {} -f", bin)?;
+
+    for (path, cmd, args) in commands {
+        let condition = fish_quote(&format!(
+            "test (__{}_cmd) = {}",
+            bin,
+            fish_quote(&path.join(" "))
+        ));
+
+        for sub in cmd.get_subcommands() {
+            writeln!(
+                out,
+                "complete -c {} -n {} -a {} -d {}",
+                bin,
+                condition,
+                sub.get_name(),
+                fish_quote(sub.get_description())
+            )?;
+        }
+
+        for arg in args {
+            let value = match (arg.takes_value(), arg.get_choices()) {
+                (_, Some(choices)) => format!(" -r -a {}", fish_quote(&choices.join(" "))),
+                (true, None) => String::from(" -r -F"),
+                (false, None) => String::new(),
+            };
+            writeln!(
+                out,
+                "complete -c {} -n {} -s {} -l {}{} -d {}",
+                bin,
+                condition,
+                arg.get_short(),
+                arg.get_long(),
+                value,
+                fish_quote(arg.get_description())
+            )?;
+            if let Some(negation) = arg.get_negation() {
+                writeln!(
+                    out,
+                    "complete -c {} -n {} -l {} -d {}",
+                    bin,
+                    condition,
+                    negation,
+                    fish_quote(&negation_description(arg))
+                )?;
+            }
+        }
+
+        if cmd.has_completed_positionals() {
+            writeln!(
+                out,
+                "complete -c {} -n {} -a {}",
+                bin,
+                condition,
+                fish_quote(&format!(
+                    "({})",
+                    callback(bin, path, &format!("(__{}_forwarded)", bin))
+                ))
+            )?;
+        }
+    }
+    Ok(out)
+}
@@ -1,13 +1,52 @@
-use anyhow::{Result, anyhow};
-use std::path::{Path, PathBuf};
+use anyhow::{Context, Result, anyhow};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::fs;
+use std::sync::OnceLock;
+
+use crate::layout::Layout;
+
+static PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+// each migration upgrades the raw config from version `index` to `index + 1`
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+];
+
+pub const CONFIG_VERSION: u64 = MIGRATIONS.len() as u64;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Workspace {
     pub name: Option<String>,
+    #[serde(with = "crate::ospath")]
     pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+}
+
+impl Workspace {
+    /// Custom name, or the directory name when there is none
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
 }
 
 impl AsRef<Path> for Workspace {
@@ -16,42 +55,357 @@ impl AsRef<Path> for Workspace {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
+    version: u64,
+    /// How session names are derived, see `session::DEFAULT_TEMPLATE`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_name_template: Option<String>,
+    #[serde(default, skip_serializing_if = "PickerConfig::is_empty")]
+    picker: PickerConfig,
     workspaces: Vec<Workspace>,
 }
 
-fn get_path() -> Result<PathBuf> {
-    let path = std::env::home_dir()
-        .ok_or(anyhow!("can't get home dir"))?
-        .join(".config")
-        .join("wsm")
-        .join("config.json");
+/// How the picker looks and behaves, all fields are optional. Options other
+/// than `backend` apply to fzf and skim, `prompt`, `header` and `show_preview`
+/// to the builtin picker too.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PickerConfig {
+    /// see `picker::get`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// fzf --layout: reverse (default), default or reverse-list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// fzf --height, e.g. "40%" or "20"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<String>,
+    /// fzf --border style, e.g. "rounded"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border: Option<String>,
+    /// fzf --preview-window position and size, e.g. "down:60%"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+    /// show the preview when the picker opens instead of after ctrl-t
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_preview: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// replaces the key help above the list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// passed to fzf as they are, after everything else
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// key to fzf action, e.g. "ctrl-y": "execute-silent(echo {4} | pbcopy)"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bindings: BTreeMap<String, String>,
+}
+
+impl PickerConfig {
+    fn is_empty(&self) -> bool {
+        self.backend.is_none()
+            && self.layout.is_none()
+            && self.height.is_none()
+            && self.border.is_none()
+            && self.preview.is_none()
+            && self.show_preview.is_none()
+            && self.prompt.is_none()
+            && self.header.is_none()
+            && self.args.is_empty()
+            && self.bindings.is_empty()
+    }
+
+    // options set in the higher layer win, bindings are merged by key
+    fn merge(&mut self, other: PickerConfig) {
+        fn set<T>(field: &mut Option<T>, other: Option<T>) {
+            if other.is_some() {
+                *field = other;
+            }
+        }
+
+        set(&mut self.backend, other.backend);
+        set(&mut self.layout, other.layout);
+        set(&mut self.height, other.height);
+        set(&mut self.border, other.border);
+        set(&mut self.preview, other.preview);
+        set(&mut self.show_preview, other.show_preview);
+        set(&mut self.prompt, other.prompt);
+        set(&mut self.header, other.header);
+        if !other.args.is_empty() {
+            self.args = other.args;
+        }
+        self.bindings.extend(other.bindings);
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            session_name_template: None,
+            picker: PickerConfig::default(),
+            workspaces: vec![],
+        }
+    }
+}
+
+// unversioned configs, workspaces were bare paths before `name` was added
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) -> Result<()> {
+    let workspaces = config
+        .entry("workspaces")
+        .or_insert_with(|| Value::Array(vec![]))
+        .as_array_mut()
+        .ok_or(anyhow!("'workspaces' is not a list"))?;
+
+    for ws in workspaces.iter_mut() {
+        if let Value::String(path) = ws {
+            *ws = serde_json::json!({ "path": path });
+        }
+
+        let ws = ws
+            .as_object_mut()
+            .ok_or(anyhow!("workspace entry is not an object"))?;
+        ws.entry("name").or_insert(Value::Null);
+    }
+
+    Ok(())
+}
+
+// v2 adds optional workspace layouts, nothing to convert but older wsm
+// versions must not rewrite the file and drop them
+fn migrate_v1_to_v2(_config: &mut Map<String, Value>) -> Result<()> {
+    Ok(())
+}
+
+// v3 adds the optional session_name_template
+fn migrate_v2_to_v3(_config: &mut Map<String, Value>) -> Result<()> {
+    Ok(())
+}
+
+// v4 adds the optional picker section
+fn migrate_v3_to_v4(_config: &mut Map<String, Value>) -> Result<()> {
+    Ok(())
+}
+
+// parses the config and upgrades it to CONFIG_VERSION, returns the version
+// the file had on disk if a migration was applied
+fn parse(config_str: &str) -> Result<(Config, Option<u64>)> {
+    let mut value: Value = serde_json::from_str(config_str)?;
+    let raw = value
+        .as_object_mut()
+        .ok_or(anyhow!("config is not a json object"))?;
+
+    let version = match raw.get("version") {
+        None => 0,
+        Some(v) => v.as_u64().ok_or(anyhow!("'version' is not a number"))?,
+    };
+
+    if version > CONFIG_VERSION {
+        return Err(anyhow!(
+            "config version {} is newer than supported version {}, upgrade wsm",
+            version,
+            CONFIG_VERSION
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(raw)?;
+    }
+    raw.insert("version".to_string(), Value::from(CONFIG_VERSION));
+
+    let config: Config = serde_json::from_value(value)?;
+    let migrated_from = (version < CONFIG_VERSION).then_some(version);
+
+    Ok((config, migrated_from))
+}
+
+/// Use `path` as the user config instead of the default location (--config)
+pub fn set_path(path: PathBuf) {
+    let _ = PATH_OVERRIDE.set(path);
+}
+
+fn non_empty_env(key: &str) -> Option<PathBuf> {
+    std::env::var_os(key)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+// user config: --config, $WSM_CONFIG, $XDG_CONFIG_HOME/wsm, ~/.config/wsm
+pub fn get_path() -> Result<PathBuf> {
+    if let Some(path) = PATH_OVERRIDE.get() {
+        return Ok(path.clone());
+    }
+
+    if let Some(path) = non_empty_env("WSM_CONFIG") {
+        return Ok(path);
+    }
+
+    let config_home = match non_empty_env("XDG_CONFIG_HOME") {
+        Some(dir) => dir,
+        None => std::env::home_dir()
+            .ok_or(anyhow!("can't get home dir"))?
+            .join(".config"),
+    };
+
+    Ok(config_home.join("wsm").join("config.json"))
+}
+
+// system/team config: $WSM_SYSTEM_CONFIG or wsm/config.json in $XDG_CONFIG_DIRS
+fn get_system_path() -> Option<PathBuf> {
+    if let Some(path) = non_empty_env("WSM_SYSTEM_CONFIG") {
+        return Some(path);
+    }
+
+    let dirs = non_empty_env("XDG_CONFIG_DIRS").unwrap_or(PathBuf::from("/etc/xdg"));
+    std::env::split_paths(&dirs)
+        .map(|dir| dir.join("wsm").join("config.json"))
+        .find(|path| path.exists())
+}
+
+// override config: $WSM_CONFIG_OVERRIDE or config.override.json next to the user config
+fn get_override_path(user_path: &Path) -> PathBuf {
+    non_empty_env("WSM_CONFIG_OVERRIDE")
+        .unwrap_or_else(|| user_path.with_file_name("config.override.json"))
+}
+
+// sibling file with the given extension appended, e.g. config.json.lock
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// advisory lock on a sidecar file, the file itself is replaced on every
+// save so locking it directly would lock a stale inode
+pub fn lock(path: &Path) -> Result<fs::File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let lock_path = with_suffix(path, ".lock");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("can't open lock file: {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("can't lock: {}", lock_path.display()))?;
 
-    Ok(path)
+    Ok(file)
+}
+
+// write json to a temp file and rename it over path, caller must hold the lock.
+// A symlinked path (dotfile managers) is replaced at its target and the
+// permissions of the existing file are kept.
+pub fn write_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let path = match fs::canonicalize(path) {
+        Ok(target) => target,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e).with_context(|| format!("can't resolve: {}", path.display())),
+    };
+    let tmp_path = with_suffix(&path, ".tmp");
+
+    let mut writer = fs::File::create(&tmp_path)?;
+    if let Ok(metadata) = fs::metadata(&path) {
+        writer.set_permissions(metadata.permissions())?;
+    }
+    serde_json::to_writer(&mut writer, value)?;
+    writer.sync_all()?;
+
+    fs::rename(&tmp_path, &path).with_context(|| format!("can't replace: {}", path.display()))?;
+    Ok(())
 }
 
 impl Config {
+    /// Merged view of all config layers, from lowest to highest precedence:
+    /// system/team config, user config, override config. A workspace from a
+    /// higher layer replaces the one with the same path from a lower layer,
+    /// settings from a higher layer win when they are set.
     pub fn load() -> Result<Self> {
-        let config_path = get_path()?;
+        let user_path = get_path()?;
+        let (mut config, migrated_from) = Self::load_from(&user_path)?;
+
+        // persist the upgrade, modify re-reads and backs up under the lock
+        if migrated_from.is_some() {
+            Self::modify(|_| Ok(()))?;
+        }
+
+        if let Some(system_path) = get_system_path() {
+            let (mut system, _) = Self::load_from(&system_path)?;
+            system.merge(config);
+            config = system;
+        }
+
+        let (overrides, _) = Self::load_from(&get_override_path(&user_path))?;
+        config.merge(overrides);
+
+        Ok(config)
+    }
+
+    fn merge(&mut self, other: Config) {
+        if other.session_name_template.is_some() {
+            self.session_name_template = other.session_name_template;
+        }
+        self.picker.merge(other.picker);
 
+        for ws in other.workspaces {
+            match self.workspaces.iter_mut().find(|w| w.path == ws.path) {
+                Some(existing) => *existing = ws,
+                None => self.workspaces.push(ws),
+            }
+        }
+    }
+
+    fn load_from(config_path: &Path) -> Result<(Self, Option<u64>)> {
         if !config_path.exists() {
-            return Ok(Config { workspaces: vec![] });
+            return Ok((Config::default(), None));
         }
 
         let config_str = fs::read_to_string(config_path)?;
-        let config: Config = serde_json::from_str(&config_str)?;
-        Ok(config)
+        parse(&config_str).with_context(|| format!("can't load config: {}", config_path.display()))
+    }
+
+    // keeps the file as it was before migration next to the config
+    fn backup(config_path: &Path, version: u64) -> Result<()> {
+        let backup_path = with_suffix(config_path, &format!(".v{}.bak", version));
+        fs::copy(config_path, &backup_path)
+            .with_context(|| format!("can't back up config: {}", backup_path.display()))?;
+
+        eprintln!(
+            "Migrated config from version {} to {}, backup saved to {}",
+            version,
+            CONFIG_VERSION,
+            backup_path.display()
+        );
+        Ok(())
     }
 
-    pub fn save(&self) -> Result<()> {
+    /// Load-modify-save cycle of the user config under an exclusive lock. The
+    /// config is re-read after the lock is taken so concurrent wsm processes
+    /// don't overwrite each other's changes.
+    pub fn modify<T>(f: impl FnOnce(&mut Config) -> Result<T>) -> Result<T> {
         let config_path = get_path()?;
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+        let _lock = lock(&config_path)?;
+
+        let (mut config, migrated_from) = Self::load_from(&config_path)?;
+        if let Some(version) = migrated_from {
+            Self::backup(&config_path, version)?;
         }
 
-        let writer = fs::File::create(config_path)?;
-        Ok(serde_json::to_writer(writer, &self)?)
+        let result = f(&mut config)?;
+        write_atomic(&config_path, &config)?;
+
+        Ok(result)
+    }
+
+    pub fn get_session_name_template(&self) -> Option<&str> {
+        self.session_name_template.as_deref()
+    }
+
+    pub fn get_picker(&self) -> &PickerConfig {
+        &self.picker
     }
 
     pub fn has_ws(&self, path: &std::path::Path) -> bool {
@@ -67,9 +421,30 @@ impl Config {
         self.workspaces.push(Workspace {
             name,
             path: p.to_path_buf(),
+            layout: None,
         });
     }
 
+    pub fn rename_ws(&mut self, path: &Path, name: Option<String>) -> bool {
+        match self.workspaces.iter_mut().find(|ws| ws.path == path) {
+            Some(ws) => {
+                ws.name = name;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_ws_layout(&mut self, path: &Path, layout: Layout) -> bool {
+        match self.workspaces.iter_mut().find(|ws| ws.path == path) {
+            Some(ws) => {
+                ws.layout = Some(layout);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn remove_ws(&mut self, path: &std::path::Path) -> bool {
         let original_len = self.workspaces.len();
         self.workspaces.retain(|ws| ws.path != path);
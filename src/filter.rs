@@ -0,0 +1,94 @@
+use anyhow::{Context, Result, anyhow};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    config::{Config, Workspace},
+    fuzzy,
+    picker::{self, Picker, Selection},
+};
+
+/// Line filter in the style of fzy: the workspaces are written to its stdin one
+/// per line, it draws on the terminal itself and prints the selected lines
+pub struct Filter {
+    program: String,
+    args: Vec<String>,
+}
+
+impl Filter {
+    /// `command` is split on whitespace, e.g. "fzy --lines 20"
+    pub fn new(command: &str) -> Result<Self> {
+        let mut words = command.split_whitespace().map(|w| w.to_string());
+        let program = words
+            .next()
+            .ok_or(anyhow!("filter picker needs a command"))?;
+
+        Ok(Filter {
+            program,
+            args: words.collect(),
+        })
+    }
+}
+
+impl Picker for Filter {
+    fn pick(
+        &self,
+        config: &Config,
+        workspaces: &[(usize, &Workspace)],
+        query: Option<&str>,
+        multi: bool,
+    ) -> Result<Selection> {
+        let lines = picker::plain_lines(config, workspaces)?;
+
+        // filters don't share a flag for the initial query, narrow the list
+        // down instead unless nothing would be left
+        let mut shown: Vec<usize> = (0..lines.len()).collect();
+        if let Some(query) = query {
+            let matching: Vec<usize> = shown
+                .iter()
+                .copied()
+                .filter(|i| fuzzy::score(query, &lines[*i]).is_some())
+                .collect();
+            if !matching.is_empty() {
+                shown = matching;
+            }
+        }
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("can't run {}", self.program))?;
+
+        {
+            let mut stdin = child
+                .stdin
+                .take()
+                .with_context(|| format!("Failed to open {} stdin", self.program))?;
+            let input: Vec<&str> = shown.iter().map(|i| lines[*i].as_str()).collect();
+            stdin.write_all(input.join("\n").as_bytes())?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("can't get output from {}", self.program))?;
+
+        // selected lines are printed as they were given
+        let mut indices: Vec<usize> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| shown.iter().find(|i| lines[**i] == line))
+            .map(|i| workspaces[*i].0)
+            .collect();
+        if !multi {
+            indices.truncate(1);
+        }
+
+        Ok(Selection {
+            indices,
+            query: None,
+        })
+    }
+}
@@ -0,0 +1,341 @@
+use crate::config::Workspace;
+
+const MATCH: i64 = 16;
+const BOUNDARY: i64 = 8;
+const CONSECUTIVE: i64 = 8;
+const GAP: i64 = 1;
+
+// the runner-up must score at most half of the best match to skip the picker
+const CONFIDENCE_RATIO: i64 = 2;
+
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// char indexes of the candidate that matched the query, for highlighting
+    pub positions: Vec<usize>,
+}
+
+fn is_boundary(prev: Option<char>, c: char) -> bool {
+    match prev {
+        None => true,
+        Some(p) => {
+            matches!(p, '/' | '-' | '_' | '.' | ' ') || (p.is_lowercase() && c.is_uppercase())
+        }
+    }
+}
+
+/// Scores the query as a subsequence of the candidate, `None` if it isn't one.
+/// Matching is case-insensitive unless the query contains an uppercase char.
+/// Matches at word starts and runs of consecutive chars score higher, gaps
+/// between matched chars cost a little.
+pub fn score(query: &str, candidate: &str) -> Option<Match> {
+    let case_sensitive = query.chars().any(|c| c.is_uppercase());
+    let fold = |c: char| match case_sensitive {
+        true => c,
+        false => c.to_lowercase().next().unwrap_or(c),
+    };
+
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(fold)
+        .collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let candidate: Vec<char> = original.iter().copied().map(fold).collect();
+
+    if query.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: vec![],
+        });
+    }
+    if query.len() > candidate.len() {
+        return None;
+    }
+
+    let bonus: Vec<i64> = (0..original.len())
+        .map(|j| {
+            let prev = j.checked_sub(1).map(|p| original[p]);
+            if is_boundary(prev, original[j]) {
+                BOUNDARY
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    // best[i][j]: best score for query[..=i] with query[i] matched at candidate[j]
+    // from[i][j]: where query[i - 1] was matched in that best score
+    let m = candidate.len();
+    let mut best = vec![vec![None; m]; query.len()];
+    let mut from = vec![vec![0; m]; query.len()];
+
+    for j in 0..m {
+        if candidate[j] == query[0] {
+            best[0][j] = Some(MATCH + bonus[j] - GAP * j as i64);
+        }
+    }
+
+    for i in 1..query.len() {
+        // best previous match before j - 1, with the gap up to j folded in
+        let mut running: Option<(i64, usize)> = None;
+        for j in 1..m {
+            if j >= 2
+                && let Some(prev) = best[i - 1][j - 2]
+            {
+                let value = prev + GAP * (j - 2) as i64;
+                if running.is_none_or(|(r, _)| value > r) {
+                    running = Some((value, j - 2));
+                }
+            }
+
+            if candidate[j] != query[i] {
+                continue;
+            }
+
+            let consecutive = best[i - 1][j - 1].map(|s| (s + CONSECUTIVE, j - 1));
+            let gapped = running.map(|(r, k)| (r - GAP * (j - 1) as i64, k));
+            let choice = match (consecutive, gapped) {
+                (Some(c), Some(g)) => Some(if c.0 >= g.0 { c } else { g }),
+                (c, g) => c.or(g),
+            };
+
+            if let Some((prev_score, k)) = choice {
+                best[i][j] = Some(prev_score + MATCH + bonus[j]);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, s)| s.map(|s| (j, s)))
+        .max_by_key(|(_, s)| *s)?;
+
+    let mut positions = vec![j];
+    for i in (1..=last).rev() {
+        j = from[i][j];
+        positions.push(j);
+    }
+    positions.reverse();
+
+    Some(Match { score, positions })
+}
+
+/// Result of resolving a query against the workspace list
+pub enum Resolved<'a> {
+    One(&'a Workspace),
+    /// no single confident match, the picker should be shown
+    Ambiguous,
+}
+
+// what a query is matched against: the workspace name and the directory name
+fn keys(ws: &Workspace) -> Vec<String> {
+    let mut keys = vec![ws.display_name()];
+    let basename = ws
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    if !keys.contains(&basename) {
+        keys.push(basename);
+    }
+    keys
+}
+
+/// Tries exact name, then unique prefix, then fuzzy score over the workspace
+/// names and directory names
+pub fn resolve<'a>(workspaces: &[(usize, &'a Workspace)], query: &str) -> Resolved<'a> {
+    let exact: Vec<&Workspace> = workspaces
+        .iter()
+        .map(|(_, ws)| *ws)
+        .filter(|ws| keys(ws).iter().any(|k| k == query))
+        .collect();
+    if let [ws] = exact[..] {
+        return Resolved::One(ws);
+    }
+
+    let lower = query.to_lowercase();
+    let prefixed: Vec<&Workspace> = workspaces
+        .iter()
+        .map(|(_, ws)| *ws)
+        .filter(|ws| {
+            keys(ws)
+                .iter()
+                .any(|k| k.to_lowercase().starts_with(&lower))
+        })
+        .collect();
+    if let [ws] = prefixed[..] {
+        return Resolved::One(ws);
+    }
+
+    let mut scored: Vec<(i64, &Workspace)> = workspaces
+        .iter()
+        .filter_map(|(_, ws)| {
+            keys(ws)
+                .iter()
+                .filter_map(|k| score(query, k))
+                .map(|m| m.score)
+                .max()
+                .map(|s| (s, *ws))
+        })
+        .collect();
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+
+    match scored[..] {
+        [(_, ws)] => Resolved::One(ws),
+        [(best, ws), (second, _), ..] if best > 0 && best >= second * CONFIDENCE_RATIO => {
+            Resolved::One(ws)
+        }
+        _ => Resolved::Ambiguous,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn workspace(name: Option<&str>, path: &str) -> Workspace {
+        Workspace {
+            name: name.map(String::from),
+            path: PathBuf::from(path),
+            layout: None,
+        }
+    }
+
+    // path of the workspace the query resolves to, None when ambiguous
+    fn resolved(workspaces: &[Workspace], query: &str) -> Option<PathBuf> {
+        let entries: Vec<(usize, &Workspace)> = workspaces.iter().enumerate().collect();
+        match resolve(&entries, query) {
+            Resolved::One(ws) => Some(ws.path.clone()),
+            Resolved::Ambiguous => None,
+        }
+    }
+
+    #[test]
+    fn score_needs_a_subsequence() {
+        assert!(score("abc", "axbxc").is_some());
+        assert!(score("acb", "abc").is_none());
+        assert!(score("abcd", "abc").is_none());
+    }
+
+    #[test]
+    fn score_empty_query_matches_everything() {
+        let m = score("", "anything").unwrap();
+        assert_eq!(m.score, 0);
+        assert!(m.positions.is_empty());
+    }
+
+    #[test]
+    fn score_is_smart_case() {
+        assert!(score("api", "API").is_some());
+        assert!(score("Api", "api").is_none());
+        assert!(score("Api", "Api").is_some());
+    }
+
+    #[test]
+    fn score_prefers_consecutive_chars() {
+        let consecutive = score("abc", "abcxx").unwrap();
+        let gapped = score("abc", "axbxc").unwrap();
+        assert!(consecutive.score > gapped.score);
+        assert_eq!(consecutive.positions, vec![0, 1, 2]);
+        assert_eq!(gapped.positions, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn score_prefers_word_starts() {
+        let boundary = score("ap", "api-server").unwrap();
+        let middle = score("ap", "map").unwrap();
+        assert!(boundary.score > middle.score);
+
+        let m = score("s", "api-server").unwrap();
+        assert_eq!(m.positions, vec![4]);
+    }
+
+    #[test]
+    fn resolve_exact_name_wins_over_prefix() {
+        let workspaces = [
+            workspace(Some("api"), "/code/api"),
+            workspace(Some("api-gateway"), "/code/gateway"),
+        ];
+        assert_eq!(
+            resolved(&workspaces, "api"),
+            Some(PathBuf::from("/code/api"))
+        );
+    }
+
+    #[test]
+    fn resolve_matches_directory_names() {
+        let workspaces = [
+            workspace(Some("custom"), "/code/project"),
+            workspace(None, "/code/other"),
+        ];
+        assert_eq!(
+            resolved(&workspaces, "project"),
+            Some(PathBuf::from("/code/project"))
+        );
+    }
+
+    #[test]
+    fn resolve_unique_prefix_ignores_case() {
+        let workspaces = [workspace(None, "/code/web"), workspace(None, "/code/api")];
+        assert_eq!(
+            resolved(&workspaces, "WE"),
+            Some(PathBuf::from("/code/web"))
+        );
+    }
+
+    #[test]
+    fn resolve_shared_prefix_is_ambiguous() {
+        let workspaces = [
+            workspace(None, "/code/api-gateway"),
+            workspace(None, "/code/api-server"),
+        ];
+        assert_eq!(resolved(&workspaces, "api"), None);
+    }
+
+    #[test]
+    fn resolve_single_fuzzy_match() {
+        let workspaces = [
+            workspace(None, "/code/backend"),
+            workspace(None, "/code/frontend"),
+        ];
+        assert_eq!(
+            resolved(&workspaces, "bknd"),
+            Some(PathBuf::from("/code/backend"))
+        );
+    }
+
+    #[test]
+    fn resolve_close_fuzzy_matches_are_ambiguous() {
+        let workspaces = [
+            workspace(None, "/code/backend"),
+            workspace(None, "/code/frontend"),
+        ];
+        assert_eq!(resolved(&workspaces, "end"), None);
+    }
+
+    #[test]
+    fn resolve_clear_fuzzy_winner() {
+        // both match, but the second only far in and scattered, scoring less
+        // than half of the first
+        let workspaces = [
+            workspace(None, "/code/web-socket-server"),
+            workspace(None, "/photos-from-the-long-vacation-and-towns-visited"),
+        ];
+        assert_eq!(
+            resolved(&workspaces, "wss"),
+            Some(PathBuf::from("/code/web-socket-server"))
+        );
+    }
+
+    #[test]
+    fn resolve_without_matches_is_ambiguous() {
+        let workspaces = [workspace(None, "/code/api")];
+        assert_eq!(resolved(&workspaces, "xyz"), None);
+    }
+}
@@ -4,70 +4,199 @@ use std::{
     process::{Command, Stdio},
 };
 
-use crate::config::Workspace;
-
-pub fn call_fzf_with_workspaces(workspaces: &[Workspace]) -> Result<Option<&Workspace>> {
-    let mut child = Command::new("fzf")
-        .arg("--layout=reverse") // Puts the input at the top
-        .arg("--preview")
-        .arg(
-            "sh -c '
-    sess=$(basename {2..}); 
-    if tmux has-session -t \"$sess\" 2>/dev/null; then
-        tmux list-windows -t \"$sess\" -F \"#I:#W\" | while read -r line; do
-            index=$(echo $line | cut -d: -f1);
-            name=$(echo $line | cut -d: -f2);
-            printf \"\\033[32m── Window $index: $name ──\\033[0m\\n\";
-            tmux capture-pane -pt \"$sess:$index\" -eS -5 -E 10 | sed \"s/^/  /\";
-            echo \"\";
-        done;
-    else
-        printf \"\\033[33m--- Session Not Active ---\\033[0m\\n\";
-        ls -p --color=always {2..};
-    fi
-'",
-        )
-        .arg("--preview-window")
-        .arg("hidden")
-        .arg("--bind")
-        .arg("ctrl-t:toggle-preview")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    let input = workspaces
-        .iter()
-        .enumerate()
-        .map(|(i, ws)| {
-            let name = match &ws.name {
-                Some(n) => n,
-                None => ws
-                    .path
-                    .file_name()
-                    .and_then(|os| os.to_str())
-                    .with_context(|| {
-                        format!("can not get name for path: {}", ws.path.to_string_lossy())
-                    })?,
-            };
-            Ok(format!("{} {} {}", i, name, ws.path.to_string_lossy()))
-        })
-        .collect::<Result<Vec<String>>>()?
-        .join("\n");
-
-    {
-        let mut stdin = child.stdin.take().context("Failed to open fzf stdin")?;
-        stdin.write_all(input.as_bytes())?;
+use crate::{
+    config::{self, Config, PickerConfig, Workspace},
+    picker::{self, Picker, Selection},
+    session::SessionNames,
+    shell,
+    status::Statuses,
+};
+
+// key, hidden `wsm picker` subcommand, fzf action to run it with, and
+// whether it acts on every marked entry
+const ACTIONS: &[(&str, &str, &str, bool)] = &[
+    ("ctrl-x", "kill", "execute-silent", true),
+    ("ctrl-d", "remove", "execute-silent", true),
+    ("ctrl-r", "rename", "execute", false),
+    ("alt-enter", "open", "execute-silent", true),
+];
+
+// between the fields of an entry, names and paths may contain spaces
+const DELIMITER: char = '\t';
+
+/// Picker input: one NUL terminated "{config index}\t{markers}\t{name}\t{path}"
+/// record per workspace, see `Statuses::markers` for the markers. Only the
+/// index is read back, so names and paths can contain anything and are shown
+/// lossily when they aren't UTF-8.
+pub fn format_entries(config: &Config, workspaces: &[(usize, &Workspace)]) -> Result<String> {
+    let session_names = SessionNames::load(config)?;
+    let statuses = Statuses::load()?;
+
+    let mut out = String::new();
+    for (i, ws) in workspaces {
+        let fields = [
+            i.to_string(),
+            statuses.markers(&session_names.get(ws), true),
+            ws.display_name(),
+            ws.path.to_string_lossy().into_owned(),
+        ];
+        out.push_str(&fields.join(&DELIMITER.to_string()));
+        out.push('\0');
     }
+    Ok(out)
+}
 
-    let output = child
-        .wait_with_output()
-        .context("can't get output from fzf")?;
+/// Runs fzf or skim, which share most of the fzf command line. Picker actions
+/// call back into hidden `wsm picker` subcommands.
+pub struct Fzf {
+    program: &'static str,
+    /// env var with the user's default options for the program
+    default_opts_env: &'static str,
+    /// field placeholder for the marked entries, or the current one if none is
+    marked_field: &'static str,
+}
+
+impl Fzf {
+    pub const FZF: Fzf = Fzf {
+        program: "fzf",
+        default_opts_env: "FZF_DEFAULT_OPTS",
+        marked_field: "{+1}",
+    };
+
+    // skim has no field placeholder for marked entries, actions only get the
+    // current one
+    pub const SKIM: Fzf = Fzf {
+        program: "sk",
+        default_opts_env: "SKIM_DEFAULT_OPTIONS",
+        marked_field: "{1}",
+    };
 
-    let workspace = String::from_utf8_lossy(&output.stdout)
-        .trim()
-        .split_once(" ")
-        .and_then(|(first, _)| first.parse::<usize>().ok())
-        .and_then(|index| workspaces.get(index));
+    // options from the picker config, defaults of wsm are left out when the
+    // user's default options set them so those win, the config wins over both
+    fn options(&self, settings: &PickerConfig, multi: bool) -> Vec<String> {
+        let default_opts = std::env::var(self.default_opts_env).unwrap_or_default();
+        let user_sets = |names: &[&str]| {
+            default_opts.split_whitespace().any(|word| {
+                let option = word.split_once('=').map_or(word, |(option, _)| option);
+                names.contains(&option)
+            })
+        };
 
-    Ok(workspace)
+        let mut options = vec![];
+        match &settings.layout {
+            Some(layout) => options.push(format!("--layout={}", layout)),
+            // puts the input at the top, --reverse rather than --layout=reverse
+            // because older skim versions only know the former
+            None if !user_sets(&["--layout", "--reverse"]) => options.push("--reverse".to_string()),
+            None => {}
+        }
+        if let Some(height) = &settings.height {
+            options.push(format!("--height={}", height));
+        }
+        if let Some(border) = &settings.border {
+            options.push(format!("--border={}", border));
+        }
+        if let Some(prompt) = &settings.prompt {
+            options.push(format!("--prompt={}", prompt));
+        }
+
+        let preview_set = settings.preview.is_some() || settings.show_preview.is_some();
+        if preview_set || !user_sets(&["--preview-window"]) {
+            let mut window = settings.preview.clone().unwrap_or("right".to_string());
+            if !settings.show_preview.unwrap_or(false) {
+                window.push_str(":hidden");
+            }
+            options.push(format!("--preview-window={}", window));
+        }
+
+        options.push(format!("--header={}", picker::header(settings, multi)));
+        // after the wsm bindings so they can be overridden
+        for (key, action) in &settings.bindings {
+            options.push(format!("--bind={}:{}", key, action));
+        }
+        options.extend(settings.args.iter().cloned());
+        options
+    }
+}
+
+impl Picker for Fzf {
+    fn pick(
+        &self,
+        config: &Config,
+        workspaces: &[(usize, &Workspace)],
+        query: Option<&str>,
+        multi: bool,
+    ) -> Result<Selection> {
+        let mut fzf = Command::new(self.program);
+        // wsm commands run by fzf must read the same config
+        fzf.env("WSM_CONFIG", config::get_path()?);
+        if let Some(query) = query {
+            fzf.arg("--query").arg(query);
+        }
+
+        let wsm = shell::quote(std::env::current_exe()?);
+        // every action reloads the list so the picker stays open on fresh data
+        let reload = format!("deselect-all+reload({} picker list)", wsm);
+        for (key, action, run, marked) in ACTIONS {
+            let field = match marked {
+                true => self.marked_field,
+                false => "{1}",
+            };
+            fzf.arg("--bind").arg(format!(
+                "{}:{}({} picker {} {})+{}",
+                key, run, wsm, action, field, reload
+            ));
+        }
+        if multi {
+            fzf.arg("--multi");
+        }
+
+        let mut child = fzf
+            .arg("--ansi")
+            .arg("--print-query")
+            .arg("--read0")
+            .arg("--print0")
+            .arg(format!("--delimiter={}", DELIMITER))
+            .arg("--with-nth=2..") // hide the index
+            .arg("--nth=2..") // of the shown fields, match the name and path
+            .arg("--preview")
+            .arg(format!("{} preview {{1}}", wsm))
+            .arg("--bind")
+            .arg("ctrl-t:toggle-preview")
+            .args(self.options(config.get_picker(), multi))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("can't run {}", self.program))?;
+
+        let input = format_entries(config, workspaces)?;
+
+        {
+            let mut stdin = child
+                .stdin
+                .take()
+                .with_context(|| format!("Failed to open {} stdin", self.program))?;
+            stdin.write_all(input.as_bytes())?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("can't get output from {}", self.program))?;
+
+        // 1 is no match, which still reports the query, 130 is cancelled
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            return Ok(Selection::default());
+        }
+
+        // --print-query puts the query in the first record
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut records = stdout.split('\0');
+        let query = records.next().map(|q| q.to_string());
+        let indices = records
+            .filter_map(|record| record.split_once(DELIMITER))
+            .filter_map(|(first, _)| first.parse::<usize>().ok())
+            .collect();
+
+        Ok(Selection { indices, query })
+    }
 }
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tmux::{self, PaneInfo};
+
+/// tmuxinator-style description of the windows and panes of a session
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    /// The first window becomes the initial window of the session
+    #[serde(default)]
+    pub windows: Vec<Window>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Window {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Working directory relative to the workspace root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// tmux layout name (main-vertical, tiled, ...) or a layout string from
+    /// `#{window_layout}`, applied after all panes are created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// A window without panes gets a single pane in its directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panes: Vec<Pane>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "PaneDef")]
+pub struct Pane {
+    /// Working directory relative to the window directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// How the pane is split off the previous one, ignored for the first pane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<Split>,
+    /// Commands typed into the pane after it is created
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    /// side by side
+    Horizontal,
+    /// stacked
+    Vertical,
+}
+
+// a pane can be written as just its command
+#[derive(Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum PaneDef {
+    Command(String),
+    Full {
+        #[serde(default)]
+        dir: Option<PathBuf>,
+        #[serde(default)]
+        split: Option<Split>,
+        #[serde(default)]
+        commands: Vec<String>,
+    },
+}
+
+impl From<PaneDef> for Pane {
+    fn from(def: PaneDef) -> Self {
+        match def {
+            PaneDef::Command(command) => Pane {
+                commands: vec![command],
+                ..Default::default()
+            },
+            PaneDef::Full {
+                dir,
+                split,
+                commands,
+            } => Pane {
+                dir,
+                split,
+                commands,
+            },
+        }
+    }
+}
+
+// panes running one of these are idle, there is nothing to restart
+const SHELLS: &[&str] = &[
+    "bash", "zsh", "fish", "sh", "dash", "ksh", "tcsh", "csh", "nu",
+];
+
+fn join_dir(base: &Path, dir: &Option<PathBuf>) -> PathBuf {
+    match dir {
+        Some(dir) => base.join(dir),
+        None => base.to_path_buf(),
+    }
+}
+
+impl Layout {
+    /// Types the commands into the first pane before its own commands
+    pub fn prepend_commands(&mut self, commands: &[String]) {
+        if commands.is_empty() {
+            return;
+        }
+
+        if self.windows.is_empty() {
+            self.windows.push(Window::default());
+        }
+        let window = &mut self.windows[0];
+        if window.panes.is_empty() {
+            window.panes.push(Pane::default());
+        }
+        let pane = &mut window.panes[0];
+        pane.commands.splice(0..0, commands.iter().cloned());
+    }
+
+    /// Reads the windows and panes of a running session. Pane directories are
+    /// stored relative to root when inside it, panes running something other
+    /// than a shell get that command as their startup command.
+    pub fn capture(session_name: &str, root: &Path) -> Result<Layout> {
+        Self::capture_with(session_name, root, |_, _| Ok(()))
+    }
+
+    /// Same as `capture`, calls `on_pane` with the window index for every
+    /// pane in layout order
+    pub fn capture_with(
+        session_name: &str,
+        root: &Path,
+        mut on_pane: impl FnMut(usize, &PaneInfo) -> Result<()>,
+    ) -> Result<Layout> {
+        let mut windows = vec![];
+        for (window_index, window) in tmux::list_windows(session_name)?.into_iter().enumerate() {
+            let panes = tmux::list_panes(&window.id)?;
+            for pane in &panes {
+                on_pane(window_index, pane)?;
+            }
+
+            let panes = panes
+                .into_iter()
+                .map(|pane| {
+                    let dir = match pane.path.strip_prefix(root) {
+                        Ok(rel) if rel.as_os_str().is_empty() => None,
+                        Ok(rel) => Some(rel.to_path_buf()),
+                        Err(_) => Some(pane.path),
+                    };
+                    let commands = match SHELLS.contains(&pane.command.as_str()) {
+                        true => vec![],
+                        false => vec![pane.command],
+                    };
+                    Pane {
+                        dir,
+                        split: None,
+                        commands,
+                    }
+                })
+                .collect();
+
+            windows.push(Window {
+                name: (!window.automatic_rename).then_some(window.name),
+                dir: None,
+                layout: Some(window.layout),
+                panes,
+            });
+        }
+
+        Ok(Layout { windows })
+    }
+
+    /// Creates a detached session with every window and pane of the layout
+    pub fn create_session(
+        &self,
+        session_name: &str,
+        root: &Path,
+        env: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let Some(first) = self.windows.first() else {
+            tmux::new_session(session_name, root, env)?;
+            return Ok(());
+        };
+
+        let first_dir = join_dir(root, &first.dir);
+        let first_pane_dir = join_dir(&first_dir, &first.panes.first().and_then(|p| p.dir.clone()));
+        tmux::new_session(session_name, &first_pane_dir, env)?;
+
+        let first_id = tmux::display(&format!("={}:^", session_name), "#{window_id}")?;
+        if let Some(name) = &first.name {
+            tmux::rename_window(&first_id, name)?;
+        }
+        Self::build_window(first, &first_id, &first_dir)?;
+
+        for window in &self.windows[1..] {
+            let dir = join_dir(root, &window.dir);
+            let pane_dir = join_dir(&dir, &window.panes.first().and_then(|p| p.dir.clone()));
+            let window_id = tmux::new_window(session_name, window.name.as_deref(), &pane_dir)?;
+            Self::build_window(window, &window_id, &dir)?;
+        }
+
+        tmux::select_window(&first_id)?;
+        Ok(())
+    }
+
+    // splits the remaining panes off the window's first pane and starts the commands
+    fn build_window(window: &Window, window_id: &str, dir: &Path) -> Result<()> {
+        let mut pane_ids = vec![tmux::display(window_id, "#{pane_id}")?];
+        for pane in window.panes.iter().skip(1) {
+            // split off the last pane so pane indexes follow the layout order
+            let previous = &pane_ids[pane_ids.len() - 1];
+            let horizontal = pane.split == Some(Split::Horizontal);
+            let pane_id = tmux::split_window(previous, &join_dir(dir, &pane.dir), horizontal)?;
+            pane_ids.push(pane_id);
+        }
+
+        if let Some(layout) = &window.layout {
+            tmux::select_layout(window_id, layout)?;
+        }
+
+        for (pane, pane_id) in window.panes.iter().zip(&pane_ids) {
+            for command in &pane.commands {
+                tmux::send_keys(pane_id, command)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,48 +1,354 @@
+mod actions;
+mod builtin;
+mod color;
 mod commands;
+mod completions;
 mod config;
+mod filter;
+mod fuzzy;
 mod fzf;
+mod layout;
+mod manifest;
+mod manpage;
+mod ospath;
+mod paths;
+mod picker;
+mod preview;
+mod prompt;
+mod session;
+mod shell;
+mod snapshot;
+mod sort;
+mod state;
+mod status;
 mod tmux;
 
 use crate::{
-    commands::{ArgType, Command, CommandDef, ParseError},
-    config::Config,
+    commands::{Arg, ArgType, Arity, Command, CommandDef, ParseError},
+    completions::Shell,
+    config::{Config, Workspace},
+    fuzzy::Resolved,
+    layout::Layout,
+    manifest::ManifestFile,
+    session::SessionNames,
+    snapshot::{SessionSnapshot, Snapshot},
+    sort::Sort,
+    state::State,
+    status::Statuses,
 };
 use anyhow::{Result, anyhow};
-use std::path::{Path, PathBuf};
+use std::{io::IsTerminal, path::PathBuf};
 
 fn define_command() -> CommandDef {
     let command = CommandDef::new(
         "wsm",
-        "Command line workspace multiplexer, add workspaces to list and swtitch between them using fzf and tmux",
+        "Command line workspace multiplexer, add workspaces to list and switch between them using fzf and tmux",
+    )
+    .add_global_arg(
+        "c",
+        "config",
+        ArgType::Value,
+        "Use this config file instead of $WSM_CONFIG or ~/.config/wsm/config.json",
     );
 
     let select = CommandDef::new(
         "select",
-        "Select a workspace in fzf and switch to tmux session(create + switch)",
+        "Select a workspace in the picker and switch to tmux session(create + switch), with a query switches directly when it matches a single workspace",
     )
     .add_arg(
         "p",
         "print",
         ArgType::Flag,
         "creates tmux workspace and prints name instead of switching",
-    );
+    )
+    .add_arg(
+        "m",
+        "multi",
+        ArgType::Flag,
+        "Mark several workspaces with tab, switch to the first and start the rest in the background",
+    )
+    .add_arg(
+        "P",
+        "picker",
+        ArgType::Value,
+        "Picker to use: fzf, skim, fzy, filter:<command>, prompt or builtin",
+    )
+    .add_positional(
+        "query",
+        ArgType::Value,
+        Arity::ZeroOrMore,
+        "Name or directory of the workspace, the picker opens when it matches none or several",
+    )
+    .add_example("wsm select", "Pick a workspace and switch to its session")
+    .add_example(
+        "wsm select api",
+        "Switch to the workspace matching api without the picker",
+    )
+    .add_example("wsm select -m", "Open several workspaces at once")
+    .complete_positionals();
     let command = command.add_subcommand(select);
 
-    let add = CommandDef::new("add", "Add a workspace to fzf").add_arg(
+    let add = CommandDef::new("add", "Add a directory as a workspace")
+        .add_arg(
+            "n",
+            "name",
+            ArgType::Value,
+            "Set specific custom name for the workspace",
+        )
+        .add_positional(
+            "path",
+            ArgType::Dir,
+            Arity::Optional,
+            "Directory to add, the current directory by default",
+        )
+        .add_example("wsm add", "Add the current directory")
+        .add_example(
+            "wsm add -n api ~/code/api",
+            "Add a directory with a custom name",
+        );
+    let command = command.add_subcommand(add);
+
+    let remove = CommandDef::new("remove", "Remove a workspace")
+        .add_positional(
+            "path",
+            ArgType::Dir,
+            Arity::Optional,
+            "Directory of the workspace, the current directory by default",
+        )
+        .complete_positionals();
+    let command = command.add_subcommand(remove);
+
+    let rename = CommandDef::new(
+        "rename",
+        "Rename a workspace, without --name the custom name is cleared",
+    )
+    .add_arg(
         "n",
         "name",
         ArgType::Value,
-        "Set specific custom name for the workspace",
+        "New custom name for the workspace",
+    )
+    .add_positional(
+        "path",
+        ArgType::Dir,
+        Arity::Optional,
+        "Directory of the workspace, the current directory by default",
+    )
+    .add_example("wsm rename -n api ~/code/api", "Name the workspace api")
+    .add_example("wsm rename ~/code/api", "Clear the custom name")
+    .complete_positionals();
+    let command = command.add_subcommand(rename);
+
+    let ls = CommandDef::new("ls", "list all workspaces added")
+        .add_arg(
+            "s",
+            "sort",
+            ArgType::Choice(Sort::CHOICES),
+            "Order by frecency, name, path or added (default)",
+        )
+        .add_arg(
+            "l",
+            "long",
+            ArgType::Flag,
+            "Also show session status, window count and session name",
+        )
+        .add_example(
+            "wsm ls -s frecency -l",
+            "Most used workspaces first, with status",
+        );
+    let command = command.add_subcommand(ls);
+
+    let allow = CommandDef::new(
+        "allow",
+        "Trust the .wsm.toml of a workspace so it is applied when the session is created",
+    )
+    .add_positional(
+        "path",
+        ArgType::Dir,
+        Arity::Optional,
+        "Directory of the workspace, the current directory by default",
     );
-    let command = command.add_subcommand(add);
+    let command = command.add_subcommand(allow);
+
+    let deny = CommandDef::new("deny", "Revoke trust for the .wsm.toml of a workspace")
+        .add_positional(
+            "path",
+            ArgType::Dir,
+            Arity::Optional,
+            "Directory of the workspace, the current directory by default",
+        );
+    let command = command.add_subcommand(deny);
+
+    let layout = CommandDef::new("layout", "Manage tmux layouts of workspaces").add_subcommand(
+        CommandDef::new(
+            "save",
+            "Save the windows and panes of a session as the layout of its workspace",
+        )
+        .add_arg(
+            "s",
+            "session",
+            ArgType::Value,
+            "Session to save instead of the current one",
+        )
+        .add_example("wsm layout save", "Save the layout of the current session"),
+    );
+    let command = command.add_subcommand(layout);
 
-    let remove = CommandDef::new("remove", "remove workspace from fzf");
-    let command = command.add_subcommand(remove);
+    let snapshot = CommandDef::new(
+        "snapshot",
+        "Save all running workspace sessions so they can be restored after a tmux restart",
+    )
+    .add_arg(
+        "l",
+        "scrollback",
+        ArgType::Int,
+        "Also save the last N lines of every pane",
+    )
+    .add_example(
+        "wsm snapshot -l 200",
+        "Also save the last 200 lines of every pane",
+    );
+    let command = command.add_subcommand(snapshot);
 
-    let ls = CommandDef::new("ls", "list all workspaces added");
-    let command = command.add_subcommand(ls);
+    let restore = CommandDef::new(
+        "restore",
+        "Recreate the sessions from the last snapshot that are not running",
+    )
+    .add_arg(
+        "f",
+        "file",
+        ArgType::Path,
+        "Restore from this snapshot file instead of the last snapshot",
+    )
+    .add_arg(
+        "a",
+        "max-age",
+        ArgType::Duration,
+        "Only restore a snapshot taken at most this long ago, e.g. 12h",
+    )
+    .add_example(
+        "wsm restore -a 1d",
+        "Restore unless the last snapshot is more than a day old",
+    );
+    let command = command.add_subcommand(restore);
+
+    let last = CommandDef::new(
+        "last",
+        "Switch back to the previously active workspace session",
+    );
+    let command = command.add_subcommand(last);
+
+    let back = CommandDef::new(
+        "back",
+        "Switch to the workspace session N switches back (default 1)",
+    )
+    .add_positional(
+        "steps",
+        ArgType::Int,
+        Arity::Optional,
+        "Number of switches to go back",
+    )
+    .add_example(
+        "wsm back 3",
+        "Switch to the session entered three switches ago",
+    );
+    let command = command.add_subcommand(back);
+
+    let completions = CommandDef::new(
+        "completions",
+        "Print the completion script for bash, zsh or fish",
+    )
+    .add_positional(
+        "shell",
+        ArgType::Choice(Shell::CHOICES),
+        Arity::Required,
+        "Shell to print the script for",
+    )
+    .add_example(
+        "eval \"$(wsm completions bash)\"",
+        "Enable completions in bash",
+    );
+    let command = command.add_subcommand(completions);
+
+    let manpage = CommandDef::new("manpage", "Print the man page of wsm in roff")
+        .add_arg(
+            "m",
+            "markdown",
+            ArgType::Flag,
+            "Print the same reference in markdown",
+        )
+        .add_example(
+            "wsm manpage > ~/.local/share/man/man1/wsm.1",
+            "Install the man page",
+        );
+    let command = command.add_subcommand(manpage);
+
+    let track = CommandDef::new(
+        "track",
+        "Record a session switch in the history, for the tmux client-session-changed hook",
+    )
+    .add_positional(
+        "session",
+        ArgType::Value,
+        Arity::Optional,
+        "Session that was entered, the current one by default",
+    )
+    .hidden();
+    let command = command.add_subcommand(track);
+
+    let preview = CommandDef::new(
+        "preview",
+        "Render the picker preview for the workspace at the given config index",
+    )
+    .add_positional(
+        "index",
+        ArgType::Int,
+        Arity::Required,
+        "Config index of the workspace",
+    )
+    .hidden();
+    let command = command.add_subcommand(preview);
 
-    command
+    let picker = CommandDef::new(
+        "picker",
+        "Actions bound to picker keys, workspaces are given by config index",
+    )
+    .add_subcommand(CommandDef::new("list", "Print the picker entries"))
+    .add_subcommand(picker_action("kill", "Kill the session of the workspace"))
+    .add_subcommand(picker_action("remove", "Remove the workspace"))
+    .add_subcommand(picker_action(
+        "rename",
+        "Prompt for a new name for the workspace",
+    ))
+    .add_subcommand(picker_action(
+        "open",
+        "Create the session of the workspace without switching to it",
+    ))
+    .hidden();
+    let command = command.add_subcommand(picker);
+
+    let complete = CommandDef::new(
+        "complete",
+        "Print the workspaces the given command can be completed with, for completion scripts",
+    )
+    .add_positional(
+        "command",
+        ArgType::Value,
+        Arity::Required,
+        "Command being completed",
+    )
+    .hidden();
+    command.add_subcommand(complete)
+}
+
+// picker actions act on the marked workspaces or the current one
+fn picker_action(name: &'static str, description: &'static str) -> CommandDef {
+    CommandDef::new(name, description).add_positional(
+        "indices",
+        ArgType::Int,
+        Arity::OneOrMore,
+        "Config indices of the workspaces",
+    )
 }
 
 fn handle_command() -> Result<()> {
@@ -54,7 +360,9 @@ fn handle_command() -> Result<()> {
                 ParseError::UnknownArg { path, name: _ } => path,
                 ParseError::MissingArgValue { path, name: _ } => path,
                 ParseError::UnexpectedArgValue { path, name: _ } => path,
+                ParseError::InvalidArgValue { path, .. } => path,
                 ParseError::MissingValue { path, name: _ } => path,
+                ParseError::UnexpectedValue { path, value: _ } => path,
                 ParseError::HelpRequested { path } => path,
             };
             if let ParseError::HelpRequested { path: _ } = err {
@@ -67,13 +375,30 @@ fn handle_command() -> Result<()> {
         Ok(command) => anyhow::Ok(command),
     }?;
 
+    if let Some(config_path) = command.get_arg_value("config") {
+        config::set_path(PathBuf::from(config_path));
+    }
+
     let path = &command.get_path()[1..];
 
     let cmd_result = match path {
         ["select"] => handle_ws_select(&command),
         ["add"] => handle_add(&command),
         ["remove"] => handle_remove(&command),
-        ["ls"] => handle_ls(),
+        ["rename"] => handle_rename(&command),
+        ["ls"] => handle_ls(&command),
+        ["allow"] => handle_allow(&command),
+        ["deny"] => handle_deny(&command),
+        ["layout", "save"] => handle_layout_save(&command),
+        ["snapshot"] => handle_snapshot(&command),
+        ["restore"] => handle_restore(&command),
+        ["last"] | ["back"] => handle_back(&command),
+        ["track"] => handle_track(&command),
+        ["completions"] => handle_completions(&command_def, &command),
+        ["manpage"] => handle_manpage(&command_def, &command),
+        ["complete"] => handle_complete(&command),
+        ["preview"] => handle_preview(&command),
+        ["picker", action] => handle_picker(action, &command),
         _ => Err(anyhow!("Command not found")),
     };
 
@@ -85,40 +410,25 @@ fn handle_command() -> Result<()> {
 }
 
 fn main() {
-    if let Err(_) = handle_command() {
+    if handle_command().is_err() {
         std::process::exit(1)
     }
 
     std::process::exit(0)
 }
 
-fn get_path_from_str(val: &str) -> Result<PathBuf> {
-    let path = match val {
-        "" => std::env::current_dir()?,
-        _ => PathBuf::from(val).canonicalize()?,
-    };
-
-    if !path.is_dir() {
-        Err(anyhow!("path is not a directory"))?;
+// the workspace directory given to a command, the current directory without one
+fn get_dir(cmd: &Command) -> Result<PathBuf> {
+    match cmd.get_positional("path").and_then(Arg::as_path) {
+        Some(path) => Ok(path.to_path_buf()),
+        None => Ok(std::env::current_dir()?),
     }
-
-    Ok(path)
 }
 
 fn handle_add(cmd: &Command) -> Result<()> {
-    let positional = cmd.get_positional_string();
-    let path = get_path_from_str(&positional)?;
-
-    let name = cmd.get_arg_value("name");
+    let path = get_dir(cmd)?;
 
-    let mut config = Config::load()?;
-
-    if config.has_ws(&path) {
-        return Err(anyhow!("workspace already exists"));
-    }
-
-    config.add_ws(&path, name.map(|s| s.to_string()));
-    config.save()?;
+    let (name, untrusted) = actions::add(&path, cmd.get_arg_value("name"))?;
 
     println!(
         "Added workspace: {} {}",
@@ -128,75 +438,506 @@ fn handle_add(cmd: &Command) -> Result<()> {
             None => String::from(""),
         }
     );
+
+    if let Some(manifest) = untrusted {
+        println!(
+            "Found {}, run `wsm allow {}` to apply it when the session is created",
+            manifest.path.display(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn handle_allow(cmd: &Command) -> Result<()> {
+    let path = get_dir(cmd)?;
+    let manifest = ManifestFile::find(&path)?.ok_or(anyhow!(
+        "no {} in {}",
+        manifest::FILE_NAME,
+        path.display()
+    ))?;
+
+    State::modify(|state| {
+        state.trust(&manifest.path, &manifest.hash);
+        Ok(())
+    })?;
+
+    println!("Allowed: {}", manifest.path.display());
+    Ok(())
+}
+
+fn handle_deny(cmd: &Command) -> Result<()> {
+    let path = get_dir(cmd)?;
+    let manifest_path = path.join(manifest::FILE_NAME);
+
+    let removed = State::modify(|state| Ok(state.untrust(&manifest_path)))?;
+    if !removed {
+        return Err(anyhow!("{} is not allowed", manifest_path.display()));
+    }
+
+    println!("Denied: {}", manifest_path.display());
     Ok(())
 }
 
-fn handle_ls() -> Result<()> {
+fn handle_ls(cmd: &Command) -> Result<()> {
+    let sort = match cmd.get_arg_choice("sort") {
+        Some(index) => Sort::ALL[index],
+        None => Sort::Added,
+    };
+
     let config = Config::load()?;
-    let workspaces = config.get_ws_all();
-    for ws in workspaces {
-        println!("{}", ws.path.display())
+    let state = State::load()?;
+    let workspaces = sort::sort_workspaces(config.get_ws_all(), sort, &state);
+
+    if cmd.get_arg("long").is_none() {
+        for (_, ws) in workspaces {
+            println!("{}", ws.path.display())
+        }
+        return Ok(());
+    }
+
+    let session_names = SessionNames::load(&config)?;
+    let statuses = Statuses::load()?;
+    let color = std::io::stdout().is_terminal();
+    let names: Vec<String> = workspaces
+        .iter()
+        .map(|(_, ws)| session_names.get(ws))
+        .collect();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+
+    for ((_, ws), name) in workspaces.iter().zip(&names) {
+        println!(
+            "{} {:<width$} {}",
+            statuses.markers(name, color),
+            name,
+            ws.path.display()
+        )
     }
     Ok(())
 }
 
 fn handle_remove(cmd: &Command) -> Result<()> {
-    let positional = cmd.get_positional_string();
-    let path = get_path_from_str(&positional)?;
-    let mut config = Config::load()?;
+    let path = get_dir(cmd)?;
+    actions::remove(&path)?;
+
+    println!("Removed workspace: {}", path.display());
+    Ok(())
+}
+
+fn handle_rename(cmd: &Command) -> Result<()> {
+    let path = get_dir(cmd)?;
+    let name = cmd.get_arg_value("name").map(|s| s.to_string());
 
-    if (config.has_ws(&path)) == false {
-        return Err(anyhow!("workspace does not exist"));
+    actions::rename(&path, name.clone())?;
+
+    match name {
+        Some(name) => println!("Renamed workspace: {} to {}", path.display(), name),
+        None => println!("Cleared name of workspace: {}", path.display()),
     }
+    Ok(())
+}
 
-    config.remove_ws(&path);
-    config.save()?;
+fn handle_layout_save(cmd: &Command) -> Result<()> {
+    let session_name = match cmd.get_arg_value("session") {
+        Some(name) => name.to_string(),
+        None => tmux::current_session().ok_or(anyhow!("not in tmux, pass --session"))?,
+    };
 
-    println!("Removed workspace: {}", path.display());
+    let config = Config::load()?;
+    let workspace = SessionNames::load(&config)?
+        .find(&config, &session_name)
+        .ok_or(anyhow!("no workspace for session {}", session_name))?;
+
+    let layout = Layout::capture(&session_name, &workspace.path)?;
+    let windows = layout.windows.len();
+
+    Config::modify(|config| {
+        if !config.set_ws_layout(&workspace.path, layout) {
+            return Err(anyhow!(
+                "workspace {} is not in the user config",
+                workspace.path.display()
+            ));
+        }
+        Ok(())
+    })?;
+
+    println!(
+        "Saved layout with {} windows for {}",
+        windows,
+        workspace.path.display()
+    );
+    Ok(())
+}
+
+fn handle_snapshot(cmd: &Command) -> Result<()> {
+    let scrollback = cmd.get_arg_int("scrollback").unwrap_or(0);
+
+    let config = Config::load()?;
+    let session_names = SessionNames::load(&config)?;
+    let running = tmux::list_sessions()?;
+
+    let mut sessions = vec![];
+    for ws in config.get_ws_all() {
+        let session_name = session_names.get(ws);
+        if running.contains(&session_name) {
+            sessions.push(SessionSnapshot::take(&session_name, &ws.path, scrollback)?);
+        }
+    }
+
+    let count = sessions.len();
+    let path = Snapshot::new(sessions).save()?;
+    println!("Saved {} sessions to {}", count, path.display());
+    Ok(())
+}
+
+fn handle_restore(cmd: &Command) -> Result<()> {
+    let snapshot = match cmd.get_arg_path("file") {
+        Some(path) => Snapshot::load_from(path)?,
+        None => Snapshot::load()?,
+    };
+
+    if let Some(max_age) = cmd.get_arg_duration("max-age")
+        && state::now().saturating_sub(snapshot.created) > max_age.as_secs()
+    {
+        println!("Not restoring, the snapshot is older than --max-age");
+        return Ok(());
+    }
+
+    for session in &snapshot.sessions {
+        if tmux::has_session(&session.name)? {
+            println!("Already running: {}", session.name);
+            continue;
+        }
+
+        match session.restore() {
+            Ok(()) => println!("Restored: {}", session.name),
+            Err(e) => eprintln!("Can't restore {}: {:#}", session.name, e),
+        }
+    }
     Ok(())
 }
 
 // print session name instead of switch_client
 fn handle_ws_select(cmd: &Command) -> Result<()> {
     let only_print_session_name = cmd.get_arg("print").is_some();
+    let multi = cmd.get_arg("multi").is_some();
+
+    let config = Config::load()?;
+    let workspaces = sort::sort_workspaces(config.get_ws_all(), Sort::Frecency, &State::load()?);
+
+    let query: Vec<&str> = cmd
+        .get_positionals("query")
+        .iter()
+        .filter_map(Arg::as_str)
+        .collect();
+    let query = query.join(" ");
+    let query = (!query.is_empty()).then_some(query.as_str());
+
+    let resolved = match query {
+        Some(query) => fuzzy::resolve(&workspaces, query),
+        None => Resolved::Ambiguous,
+    };
+
+    let paths: Vec<PathBuf> = match resolved {
+        Resolved::One(ws) => vec![ws.path.clone()],
+        Resolved::Ambiguous => {
+            let selection = picker::get(&config, cmd.get_arg_value("picker"))?.pick(
+                &config,
+                &workspaces,
+                query,
+                multi,
+            )?;
+            let config = Config::load()?;
+            let paths: Vec<PathBuf> = selection
+                .indices
+                .iter()
+                .filter_map(|i| config.get_ws_all().get(*i))
+                .map(|ws| ws.path.clone())
+                .collect();
+
+            match (paths.is_empty(), selection.query) {
+                (true, Some(query)) => add_from_query(&query)?.into_iter().collect(),
+                _ => paths,
+            }
+        }
+    };
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    // picker actions may have changed the config while fzf was open
+    let config = Config::load()?;
+    let selected = paths
+        .iter()
+        .map(|path| {
+            config
+                .get_ws_all()
+                .iter()
+                .find(|ws| ws.path == *path)
+                .ok_or(anyhow!("workspace {} was removed", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    State::modify(|state| {
+        for path in &paths {
+            state.record_use(path);
+        }
+        Ok(())
+    })?;
+
+    // the rest are only created, attaching to the first blocks until detach
+    for workspace in &selected[1..] {
+        actions::open_detached(&config, workspace)?;
+    }
+
+    let workspace = selected[0];
+    let session_name = SessionNames::load(&config)?.get(workspace);
+    switch_to_session(&session_name, Some(workspace), only_print_session_name)
+}
+
+// offers to add the directory typed into the picker when no workspace matched
+fn add_from_query(query: &str) -> Result<Option<PathBuf>> {
+    let query = query.trim();
+    if !paths::looks_like_path(query) || !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    let dir = match &paths::candidate_dirs(query)[..] {
+        [] => return Ok(None),
+        [dir] => {
+            eprint!("Add {} as a workspace? [Y/n] ", dir.display());
+            match read_answer()?.to_lowercase().as_str() {
+                "" | "y" | "yes" => dir.clone(),
+                _ => return Ok(None),
+            }
+        }
+        dirs => {
+            for (n, dir) in dirs.iter().enumerate() {
+                eprintln!("{:>3}) {}", n + 1, dir.display());
+            }
+            eprint!("Add which directory as a workspace? (empty to cancel) ");
+            let answer = read_answer()?;
+            match answer
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| dirs.get(n.checked_sub(1)?))
+            {
+                Some(dir) => dir.clone(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let path = dir.canonicalize()?;
+    // e.g. typed through a symlink
+    if Config::load()?.has_ws(&path) {
+        return Ok(Some(path));
+    }
+
+    let (name, untrusted) = actions::add(&path, None)?;
+    eprintln!(
+        "Added workspace: {} {}",
+        path.display(),
+        match name {
+            Some(val) => format!("with name: {}", val),
+            None => String::from(""),
+        }
+    );
+    if let Some(manifest) = untrusted {
+        eprintln!(
+            "Found {}, run `wsm allow {}` to apply it when the session is created",
+            manifest.path.display(),
+            path.display()
+        );
+    }
+    Ok(Some(path))
+}
+
+fn read_answer() -> Result<String> {
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+fn handle_back(cmd: &Command) -> Result<()> {
+    let steps = match cmd.get_positional("steps").and_then(Arg::as_int) {
+        Some(0) => return Err(anyhow!("expected a positive number of steps, got 0")),
+        Some(steps) => steps,
+        None => 1,
+    };
+
+    let current = tmux::current_session().filter(|_| tmux::is_in_tmux());
+    let state = State::load()?;
+    let session_name = state
+        .get_history()
+        .iter()
+        .filter(|name| Some(*name) != current.as_ref())
+        .nth(steps - 1)
+        .ok_or(anyhow!("no workspace {} switches back", steps))?;
 
     let config = Config::load()?;
-    let workspaces = config.get_ws_all();
+    let workspace = SessionNames::load(&config)?.find(&config, session_name);
+
+    switch_to_session(session_name, workspace, false)
+}
 
-    let session_path = match fzf::call_fzf_with_workspaces(workspaces)? {
-        Some(p) => p.as_ref(),
-        None => return Ok(()),
+// called from the tmux client-session-changed hook
+fn handle_track(cmd: &Command) -> Result<()> {
+    let session_name = match cmd.get_positional("session").and_then(Arg::as_str) {
+        Some(name) => name.to_string(),
+        None => tmux::current_session().ok_or(anyhow!("not in tmux, pass a session name"))?,
     };
 
-    let session_name = get_session_name(session_path);
+    State::modify(|state| {
+        state.push_history(&session_name);
+        Ok(())
+    })
+}
+
+fn handle_completions(command_def: &CommandDef, cmd: &Command) -> Result<()> {
+    let shell = cmd
+        .get_positional("shell")
+        .and_then(Arg::as_choice)
+        .map(|index| Shell::ALL[index])
+        .ok_or(anyhow!("expected a shell"))?;
+    print!("{}", completions::generate(shell, command_def)?);
+    Ok(())
+}
+
+fn handle_manpage(command_def: &CommandDef, cmd: &Command) -> Result<()> {
+    let page = match cmd.get_arg("markdown").is_some() {
+        true => manpage::markdown(command_def)?,
+        false => manpage::roff(command_def)?,
+    };
+    print!("{}", page);
+    Ok(())
+}
+
+// names for select, paths for the commands that take a workspace directory
+fn handle_complete(cmd: &Command) -> Result<()> {
+    let config = Config::load()?;
+    let workspaces = sort::sort_workspaces(config.get_ws_all(), Sort::Frecency, &State::load()?);
+
+    let mut candidates: Vec<String> = vec![];
+    for (_, ws) in workspaces {
+        let candidate = match cmd.get_positional("command").and_then(Arg::as_str) {
+            Some("select") => ws.display_name(),
+            Some("remove" | "rename") => ws.path.to_string_lossy().into_owned(),
+            _ => continue,
+        };
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+
+    for candidate in candidates {
+        println!("{}", candidate);
+    }
+    Ok(())
+}
+
+fn handle_preview(cmd: &Command) -> Result<()> {
+    let index = cmd
+        .get_positional("index")
+        .and_then(Arg::as_int)
+        .ok_or(anyhow!("expected a workspace index"))?;
+
+    let config = Config::load()?;
+    let workspace = config
+        .get_ws_all()
+        .get(index)
+        .ok_or(anyhow!("no workspace at index {}", index))?;
+    let session_name = SessionNames::load(&config)?.get(workspace);
+
+    print!("{}", preview::render(&session_name, &workspace.path)?);
+    Ok(())
+}
+
+// run by the fzf key bindings, the list is reloaded after each action
+fn handle_picker(action: &str, cmd: &Command) -> Result<()> {
+    let config = Config::load()?;
+
+    if action == "list" {
+        let workspaces =
+            sort::sort_workspaces(config.get_ws_all(), Sort::Frecency, &State::load()?);
+        print!("{}", fzf::format_entries(&config, &workspaces)?);
+        return Ok(());
+    }
+
+    // indices are resolved up front, removing one shifts the ones after it
+    let indices: Vec<usize> = cmd
+        .get_positionals("indices")
+        .iter()
+        .filter_map(Arg::as_int)
+        .collect();
+    let workspaces = actions::workspaces_at(&config, &indices)?;
+    for workspace in workspaces {
+        match action {
+            "kill" => actions::kill(&config, workspace)?,
+            "remove" => actions::remove(&workspace.path)?,
+            "open" => actions::open_detached(&config, workspace)?,
+            "rename" => {
+                eprint!(
+                    "New name for {} (empty to clear): ",
+                    workspace.path.display()
+                );
+                let mut name = String::new();
+                std::io::stdin().read_line(&mut name)?;
+                let name = name.trim();
+                actions::rename(
+                    &workspace.path,
+                    (!name.is_empty()).then(|| name.to_string()),
+                )?
+            }
+            _ => return Err(anyhow!("unknown picker action: {}", action)),
+        }
+    }
+    Ok(())
+}
 
+// creates the session from the workspace if it is not running, then switches
+// or attaches to it, or only prints its name
+fn switch_to_session(
+    session_name: &str,
+    workspace: Option<&Workspace>,
+    only_print_session_name: bool,
+) -> Result<()> {
     let is_in_tmux = tmux::is_in_tmux();
 
     let attach_to_tmux_external = !is_in_tmux && !only_print_session_name;
     let attach_to_tmux_from_tmux = is_in_tmux && !only_print_session_name;
 
-    if is_in_tmux && tmux::is_same_tmux_session(&session_name) {
+    if is_in_tmux && tmux::is_same_tmux_session(session_name) {
         return Ok(());
     }
 
-    if !is_in_tmux || !tmux::has_session(&session_name)? {
-        tmux::new_session(&session_name, session_path, attach_to_tmux_external)?;
+    if !tmux::has_session(session_name)? {
+        let workspace = workspace.ok_or(anyhow!("session {} is not running", session_name))?;
+        session::create(session_name, workspace)?;
+    }
+
+    // keep the session we leave in the history too, it may have been
+    // entered without wsm
+    let current = tmux::current_session().filter(|_| is_in_tmux);
+    State::modify(|state| {
+        if let Some(current) = &current {
+            state.push_history(current);
+        }
+        state.push_history(session_name);
+        Ok(())
+    })?;
+
+    if attach_to_tmux_external {
+        tmux::attach_session(session_name)?;
     }
 
     if attach_to_tmux_from_tmux {
-        tmux::switch_client(&session_name)?;
+        tmux::switch_client(session_name)?;
     }
 
     if only_print_session_name {
-        println!("{}", &session_name)
+        println!("{}", session_name)
     }
 
     Ok(())
 }
-
-fn get_session_name(path: &Path) -> String {
-    path.file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .replace(".", "_")
-}
@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{layout::Window, state::State};
+
+pub const FILE_NAME: &str = ".wsm.toml";
+
+/// Project-local workspace description shipped inside a repo as `.wsm.toml`
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: Option<String>,
+    /// Environment set on the tmux session
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Commands typed into the first window after the session is created
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Session layout, used when the workspace has no layout in the config
+    #[serde(default)]
+    pub windows: Vec<Window>,
+}
+
+#[derive(Debug)]
+pub struct ManifestFile {
+    pub path: PathBuf,
+    pub hash: String,
+    pub manifest: Manifest,
+}
+
+impl ManifestFile {
+    /// Reads `.wsm.toml` from the workspace root, `None` if there is none
+    pub fn find(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let content = fs::read(&path)?;
+        let manifest = std::str::from_utf8(&content)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| Ok(toml::from_str::<Manifest>(s)?))
+            .with_context(|| format!("can't parse manifest: {}", path.display()))?;
+
+        Ok(Some(ManifestFile {
+            path,
+            hash: hash(&content),
+            manifest,
+        }))
+    }
+
+    /// Trust is keyed on content, any edit to the manifest has to be allowed again
+    pub fn is_trusted(&self, state: &State) -> bool {
+        state.is_trusted(&self.path, &self.hash)
+    }
+}
+
+fn hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
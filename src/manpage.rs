@@ -0,0 +1,159 @@
+use anyhow::Result;
+use std::fmt::Write;
+
+use crate::commands::{ArgDef, CommandDef};
+
+// roff treats backslashes and dashes specially, double quotes delimit request
+// arguments, and a line starting with a dot or a quote is a request
+fn escape(text: &str) -> String {
+    let text = text
+        .replace('\\', "\\e")
+        .replace('-', "\\-")
+        .replace('"', "\\(dq");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+fn option_line(arg: &ArgDef) -> String {
+    let mut line = format!(
+        "\\fB\\-{}\\fR, \\fB\\-\\-{}\\fR",
+        escape(arg.get_short()),
+        escape(arg.get_long())
+    );
+    if arg.takes_value() {
+        line.push_str(&format!(" \\fI{}\\fR", escape(arg.get_long())));
+    }
+    if let Some(negation) = arg.get_negation() {
+        line.push_str(&format!(", \\fB\\-\\-{}\\fR", escape(&negation)));
+    }
+    line
+}
+
+/// Man page in roff with a section per command, `man -l <(wsm manpage)`
+pub fn roff(def: &CommandDef) -> Result<String> {
+    let commands = def.walk();
+    let bin = def.get_name();
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {}\"",
+        bin.to_uppercase(),
+        bin,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out, ".SH NAME")?;
+    writeln!(out, "{} \\- {}", bin, escape(def.get_description()))?;
+
+    writeln!(out, ".SH SYNOPSIS")?;
+    for (path, cmd, args) in &commands {
+        writeln!(out, ".B {}", escape(&cmd.get_usage(path, args)))?;
+        writeln!(out, ".br")?;
+    }
+
+    for (path, cmd, args) in &commands {
+        if path.len() == 1 {
+            writeln!(out, ".SH OPTIONS")?;
+        } else {
+            writeln!(out, ".SH {}", escape(&path.join(" ").to_uppercase()))?;
+            writeln!(out, "{}", escape(cmd.get_description()))?;
+            writeln!(out, ".PP")?;
+            writeln!(out, ".B {}", escape(&cmd.get_usage(path, args)))?;
+        }
+
+        for positional in cmd.get_positionals() {
+            writeln!(out, ".TP")?;
+            writeln!(out, "\\fI{}\\fR", escape(positional.get_name()))?;
+            writeln!(out, "{}", escape(positional.get_description()))?;
+        }
+
+        for arg in args {
+            writeln!(out, ".TP")?;
+            writeln!(out, "{}", option_line(arg))?;
+            writeln!(out, "{}", escape(arg.get_description()))?;
+        }
+
+        if !cmd.get_examples().is_empty() {
+            writeln!(out, ".PP")?;
+            writeln!(out, "Examples:")?;
+        }
+        for (example, description) in cmd.get_examples() {
+            writeln!(out, ".TP")?;
+            writeln!(out, ".B {}", escape(example))?;
+            writeln!(out, "{}", escape(description))?;
+        }
+    }
+    Ok(out)
+}
+
+/// The same reference as the man page in markdown
+pub fn markdown(def: &CommandDef) -> Result<String> {
+    let commands = def.walk();
+    let mut out = String::new();
+
+    writeln!(out, "# {}", def.get_name())?;
+    writeln!(out)?;
+    writeln!(out, "{}", def.get_description())?;
+
+    for (path, cmd, args) in &commands {
+        writeln!(out)?;
+        if path.len() > 1 {
+            writeln!(out, "## {}", path.join(" "))?;
+            writeln!(out)?;
+            writeln!(out, "{}", cmd.get_description())?;
+            writeln!(out)?;
+        }
+        writeln!(out, "```")?;
+        writeln!(out, "{}", cmd.get_usage(path, args))?;
+        writeln!(out, "```")?;
+
+        if !args.is_empty() || !cmd.get_positionals().is_empty() {
+            writeln!(out)?;
+            writeln!(out, "| Argument | Description |")?;
+            writeln!(out, "|----------|-------------|")?;
+        }
+        for positional in cmd.get_positionals() {
+            writeln!(
+                out,
+                "| `{}` | {} |",
+                positional.get_usage(),
+                positional.get_description().replace('|', "\\|")
+            )?;
+        }
+        for arg in args {
+            let value = if arg.takes_value() {
+                format!(" <{}>", arg.get_long())
+            } else {
+                String::new()
+            };
+            let negation = match arg.get_negation() {
+                Some(negation) => format!(", `--{}`", negation),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "| `-{}`, `--{}{}`{} | {} |",
+                arg.get_short(),
+                arg.get_long(),
+                value,
+                negation,
+                arg.get_description().replace('|', "\\|")
+            )?;
+        }
+
+        if !cmd.get_examples().is_empty() {
+            writeln!(out)?;
+            writeln!(out, "```sh")?;
+        }
+        for (example, description) in cmd.get_examples() {
+            writeln!(out, "{}  # {}", example, description)?;
+        }
+        if !cmd.get_examples().is_empty() {
+            writeln!(out, "```")?;
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,74 @@
+//! Paths in the json files. Paths that are valid UTF-8 are stored as they are,
+//! others as a NUL char followed by the hex of their bytes. That can't clash
+//! with a real path, which never contains NUL, and keeps the files readable
+//! for the common case. Use with `#[serde(with = "ospath")]`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    ffi::OsString,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+const MARKER: char = '\0';
+
+pub fn encode(path: &Path) -> Cow<'_, str> {
+    match path.to_str() {
+        Some(s) => Cow::Borrowed(s),
+        None => {
+            let mut out = String::from(MARKER);
+            for byte in path.as_os_str().as_bytes() {
+                out.push_str(&format!("{:02x}", byte));
+            }
+            Cow::Owned(out)
+        }
+    }
+}
+
+pub fn decode(s: &str) -> Result<PathBuf, String> {
+    let Some(hex) = s.strip_prefix(MARKER) else {
+        return Ok(PathBuf::from(s));
+    };
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or(format!("invalid encoded path: {:?}", s))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    Ok(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    encode(path).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    decode(&s).map_err(D::Error::custom)
+}
+
+/// Same encoding for maps keyed by path, `#[serde(with = "ospath::keys")]`
+pub mod keys {
+    use super::*;
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &BTreeMap<PathBuf, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(path, value)| (encode(path), value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<PathBuf, V>, D::Error> {
+        BTreeMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| Ok((decode(&key).map_err(D::Error::custom)?, value)))
+            .collect()
+    }
+}
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+/// Replaces a leading `~` or `~/` with the home directory
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let home = std::env::home_dir();
+    match (path, home) {
+        ("~", Some(home)) => home,
+        (path, Some(home)) if path.starts_with("~/") => home.join(&path[2..]),
+        (path, _) => PathBuf::from(path),
+    }
+}
+
+/// Whether a picker query is meant as a path rather than a search
+pub fn looks_like_path(query: &str) -> bool {
+    query.starts_with(['/', '~', '.']) || query.contains('/')
+}
+
+/// Directories a typed path may refer to: the directory itself when it
+/// exists, otherwise the directories in its parent whose name starts with the
+/// last component, like shell completion. Hidden directories are only
+/// completed when the component starts with a dot.
+pub fn candidate_dirs(query: &str) -> Vec<PathBuf> {
+    let path = expand_tilde(query);
+    if path.is_dir() {
+        return vec![path];
+    }
+
+    let (parent, prefix) = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => (parent, name.to_string_lossy()),
+        _ => return vec![],
+    };
+    let parent = match parent.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => parent,
+    };
+
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return vec![];
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(prefix.as_ref()) && (prefix.starts_with('.') || !name.starts_with('.'))
+        })
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
@@ -0,0 +1,101 @@
+use anyhow::{Result, anyhow};
+
+use crate::{
+    builtin::Builtin,
+    config::{Config, PickerConfig, Workspace},
+    filter::Filter,
+    fzf::Fzf,
+    prompt::Prompt,
+    session::SessionNames,
+    status::Statuses,
+};
+
+const HEADER: &str =
+    "ctrl-x: kill  ctrl-d: remove  ctrl-r: rename  alt-enter: open detached  ctrl-t: preview";
+
+// tab only marks entries with --multi
+const MULTI_HEADER: &str = "  tab: mark";
+
+/// Key help above the list of fzf, skim and the builtin picker, the `header`
+/// setting replaces all of it
+pub fn header(settings: &PickerConfig, multi: bool) -> String {
+    match &settings.header {
+        Some(header) => header.clone(),
+        None if multi => format!("{}{}", HEADER, MULTI_HEADER),
+        None => HEADER.to_string(),
+    }
+}
+
+/// Interactive workspace selection, `handle_ws_select` goes through this
+/// whatever the backend is
+pub trait Picker {
+    /// `workspaces` are (config index, workspace) in the order they are shown.
+    fn pick(
+        &self,
+        config: &Config,
+        workspaces: &[(usize, &Workspace)],
+        query: Option<&str>,
+        multi: bool,
+    ) -> Result<Selection>;
+}
+
+/// Result of a picker, empty when it was cancelled
+#[derive(Default)]
+pub struct Selection {
+    /// Config indices of the selected workspaces, at most one without `multi`.
+    /// Picker actions may change the config while it is open, so they refer to
+    /// a freshly loaded config.
+    pub indices: Vec<usize>,
+    /// what was typed, for pickers that can report it
+    pub query: Option<String>,
+}
+
+/// Backend by name, from --picker or the `picker` setting. Without either it
+/// is fzf when installed, then skim, then the builtin picker.
+pub fn get(config: &Config, name: Option<&str>) -> Result<Box<dyn Picker>> {
+    let name = name.or(config.get_picker().backend.as_deref());
+    match name {
+        Some("fzf") => Ok(Box::new(Fzf::FZF)),
+        Some("skim" | "sk") => Ok(Box::new(Fzf::SKIM)),
+        Some("fzy") => Ok(Box::new(Filter::new("fzy")?)),
+        Some("prompt") => Ok(Box::new(Prompt)),
+        Some("builtin") => Ok(Box::new(Builtin)),
+        Some(other) => match other.strip_prefix("filter:") {
+            Some(command) => Ok(Box::new(Filter::new(command)?)),
+            None => Err(anyhow!(
+                "unknown picker '{}', expected fzf, skim, fzy, filter:<command>, prompt or builtin",
+                other
+            )),
+        },
+        None if is_installed("fzf") => Ok(Box::new(Fzf::FZF)),
+        None if is_installed("sk") => Ok(Box::new(Fzf::SKIM)),
+        None => Ok(Box::new(Builtin)),
+    }
+}
+
+/// Whether an executable with this name is on PATH
+pub fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// "{markers} {name} {path}" per workspace without colors, for pickers that
+/// can't show them. Control chars are replaced so every entry is one line.
+pub fn plain_lines(config: &Config, workspaces: &[(usize, &Workspace)]) -> Result<Vec<String>> {
+    let session_names = SessionNames::load(config)?;
+    let statuses = Statuses::load()?;
+
+    let lines = workspaces
+        .iter()
+        .map(|(_, ws)| {
+            let line = format!(
+                "{} {} {}",
+                statuses.markers(&session_names.get(ws), false),
+                ws.display_name(),
+                ws.path.to_string_lossy()
+            );
+            line.replace(|c: char| c.is_control(), "?")
+        })
+        .collect();
+    Ok(lines)
+}
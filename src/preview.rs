@@ -0,0 +1,68 @@
+use anyhow::Result;
+use std::{fmt::Write, fs, os::unix::fs::PermissionsExt, path::Path};
+
+use crate::{
+    color::{BOLD_BLUE, BOLD_CYAN, BOLD_GREEN, GREEN, RESET, YELLOW},
+    tmux,
+};
+
+/// Preview of a workspace for the picker: the last lines of every window when
+/// its session is running, otherwise a listing of the workspace directory
+pub fn render(session_name: &str, path: &Path) -> Result<String> {
+    match tmux::has_session(session_name)? {
+        true => render_session(session_name),
+        false => render_dir(path),
+    }
+}
+
+fn render_session(session_name: &str) -> Result<String> {
+    let mut out = String::new();
+    for window in tmux::list_windows(session_name)? {
+        writeln!(
+            out,
+            "{}── Window {}: {} ──{}",
+            GREEN, window.index, window.name, RESET
+        )?;
+        let content = tmux::capture_pane_colored(&window.id, -5, 10)?;
+        for line in content.lines() {
+            writeln!(out, "  {}", line)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(out)
+}
+
+// like `ls -p --color`: dotfiles hidden, directories get a trailing slash
+fn render_dir(path: &Path) -> Result<String> {
+    let mut out = format!("{}--- Session Not Active ---{}\n", YELLOW, RESET);
+
+    let mut entries: Vec<_> = fs::read_dir(path)?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let line = if file_type.is_dir() {
+            format!("{}{}{}/", BOLD_BLUE, name, RESET)
+        } else if file_type.is_symlink() {
+            format!("{}{}{}", BOLD_CYAN, name, RESET)
+        } else if entry
+            .metadata()
+            .is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+        {
+            format!("{}{}{}", BOLD_GREEN, name, RESET)
+        } else {
+            name.into_owned()
+        };
+        writeln!(out, "{}", line)?;
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    config::{Config, Workspace},
+    fuzzy, paths,
+    picker::{self, Picker, Selection},
+};
+
+/// Numbered list on stderr and a choice read from stdin, works on dumb
+/// terminals and needs nothing installed
+pub struct Prompt;
+
+impl Picker for Prompt {
+    fn pick(
+        &self,
+        config: &Config,
+        workspaces: &[(usize, &Workspace)],
+        query: Option<&str>,
+        multi: bool,
+    ) -> Result<Selection> {
+        let lines = picker::plain_lines(config, workspaces)?;
+        let filter = |query: &str| -> Vec<usize> {
+            (0..lines.len())
+                .filter(|i| fuzzy::score(query, &lines[*i]).is_some())
+                .collect()
+        };
+
+        let mut shown = filter(query.unwrap_or_default());
+        // same as pressing enter in fzf when nothing matches, but only for
+        // paths, anything else shows the full list again
+        if let Some(query) = query
+            && shown.is_empty()
+            && paths::looks_like_path(query)
+        {
+            return Ok(Selection {
+                indices: vec![],
+                query: Some(query.to_string()),
+            });
+        }
+        let mut stdin = io::stdin().lock();
+        let mut err = io::stderr();
+
+        loop {
+            if shown.is_empty() {
+                writeln!(err, "No matching workspaces")?;
+                shown = (0..lines.len()).collect();
+            }
+            for (n, i) in shown.iter().enumerate() {
+                writeln!(err, "{:>3}) {}", n + 1, lines[*i])?;
+            }
+
+            let hint = match multi {
+                true => "numbers separated by spaces",
+                false => "a number",
+            };
+            write!(err, "Select {}, text to filter, empty to cancel: ", hint)?;
+            err.flush()?;
+
+            let mut input = String::new();
+            if stdin.read_line(&mut input)? == 0 {
+                return Ok(Selection::default());
+            }
+            let input = input.trim();
+            if input.is_empty() {
+                return Ok(Selection::default());
+            }
+
+            let numbers: Option<Vec<usize>> = input
+                .split_whitespace()
+                .map(|word| {
+                    word.parse::<usize>()
+                        .ok()
+                        .filter(|n| (1..=shown.len()).contains(n))
+                })
+                .collect();
+
+            match numbers {
+                Some(numbers) if multi || numbers.len() == 1 => {
+                    return Ok(Selection {
+                        indices: numbers.iter().map(|n| workspaces[shown[n - 1]].0).collect(),
+                        query: None,
+                    });
+                }
+                Some(_) => writeln!(err, "Select a single workspace")?,
+                // may be a directory to add instead
+                None if filter(input).is_empty() && paths::looks_like_path(input) => {
+                    return Ok(Selection {
+                        indices: vec![],
+                        query: Some(input.to_string()),
+                    });
+                }
+                None => shown = filter(input),
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{Config, Workspace},
+    manifest::ManifestFile,
+    state::{SessionName, State},
+};
+
+/// Placeholders: {name} is the workspace name or directory name, {basename}
+/// the directory name and {parent} the name of the directory above it
+pub const DEFAULT_TEMPLATE: &str = "{name}";
+
+/// Session name of every configured workspace. Names are assigned once and
+/// kept in the state file, so a workspace keeps its session when another one
+/// with the same name is added later.
+#[derive(Debug)]
+pub struct SessionNames(BTreeMap<PathBuf, SessionName>);
+
+// tmux turns '.' and ':' into '_' and escapes control chars, names with
+// them would never match the session tmux actually created. The replacement
+// char left by lossy non-UTF-8 directory names goes too.
+pub fn sanitize(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '.' | ':' | char::REPLACEMENT_CHARACTER => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    match name.is_empty() {
+        true => "_".to_string(),
+        false => name,
+    }
+}
+
+fn file_name(path: Option<&Path>) -> String {
+    path.and_then(|p| p.file_name())
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn render(template: &str, ws: &Workspace) -> String {
+    let rendered = template
+        .replace("{name}", &ws.display_name())
+        .replace("{basename}", &file_name(Some(&ws.path)))
+        .replace("{parent}", &file_name(ws.path.parent()));
+    sanitize(&rendered)
+}
+
+// keeps still valid assignments, then gives the remaining workspaces their
+// base name or base name with the first free -N suffix, in config order
+fn assign(
+    config: &Config,
+    stored: &BTreeMap<PathBuf, SessionName>,
+) -> BTreeMap<PathBuf, SessionName> {
+    let template = config
+        .get_session_name_template()
+        .unwrap_or(DEFAULT_TEMPLATE);
+
+    let mut assigned = BTreeMap::new();
+    let mut taken = HashSet::new();
+    let mut pending = vec![];
+
+    for ws in config.get_ws_all() {
+        let base = render(template, ws);
+        match stored.get(&ws.path) {
+            Some(s) if s.base == base && !taken.contains(&s.name) => {
+                taken.insert(s.name.clone());
+                assigned.insert(ws.path.clone(), s.clone());
+            }
+            _ => pending.push((ws, base)),
+        }
+    }
+
+    for (ws, base) in pending {
+        let mut name = base.clone();
+        let mut n = 2;
+        while taken.contains(&name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+
+        taken.insert(name.clone());
+        assigned.insert(ws.path.clone(), SessionName { base, name });
+    }
+
+    assigned
+}
+
+// creates a detached session from the workspace layout, applying the
+// workspace manifest if it is trusted
+pub fn create(session_name: &str, workspace: &Workspace) -> Result<()> {
+    let session_path = workspace.path.as_path();
+    let manifest = match ManifestFile::find(session_path) {
+        Ok(Some(manifest)) if manifest.is_trusted(&State::load()?) => Some(manifest.manifest),
+        Ok(Some(manifest)) => {
+            eprintln!(
+                "{} is not trusted, run `wsm allow {}` to apply it",
+                manifest.path.display(),
+                session_path.display()
+            );
+            None
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Skipping manifest: {:#}", e);
+            None
+        }
+    };
+
+    let mut layout = workspace.layout.clone().unwrap_or_default();
+    let mut env = BTreeMap::new();
+    if let Some(manifest) = manifest {
+        if layout.windows.is_empty() {
+            layout.windows = manifest.windows;
+        }
+        layout.prepend_commands(&manifest.commands);
+        env = manifest.env;
+    }
+
+    layout.create_session(session_name, session_path, &env)
+}
+
+impl SessionNames {
+    pub fn load(config: &Config) -> Result<Self> {
+        let state = State::load()?;
+        let names = assign(config, state.get_session_names());
+        if &names == state.get_session_names() {
+            return Ok(SessionNames(names));
+        }
+
+        // re-run under the lock in case another wsm assigned names meanwhile
+        let names = State::modify(|state| {
+            let names = assign(config, state.get_session_names());
+            state.set_session_names(names.clone());
+            Ok(names)
+        })?;
+        Ok(SessionNames(names))
+    }
+
+    pub fn get(&self, ws: &Workspace) -> String {
+        match self.0.get(&ws.path) {
+            Some(s) => s.name.clone(),
+            None => render(DEFAULT_TEMPLATE, ws),
+        }
+    }
+
+    pub fn find<'a>(&self, config: &'a Config, session_name: &str) -> Option<&'a Workspace> {
+        config
+            .get_ws_all()
+            .iter()
+            .find(|ws| self.0.get(&ws.path).is_some_and(|s| s.name == session_name))
+    }
+}
@@ -0,0 +1,9 @@
+use std::ffi::OsStr;
+
+/// Quotes a word for sh, e.g. for commands fzf or tmux run through the shell
+pub fn quote<S: AsRef<OsStr>>(word: S) -> String {
+    format!(
+        "'{}'",
+        word.as_ref().to_string_lossy().replace('\'', r"'\''")
+    )
+}
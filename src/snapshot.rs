@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{lock, write_atomic},
+    layout::Layout,
+    shell, state, tmux,
+};
+
+/// Sessions of configured workspaces as they were when `wsm snapshot` ran
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Snapshot {
+    /// unix timestamp in seconds
+    pub created: u64,
+    pub sessions: Vec<SessionSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionSnapshot {
+    pub name: String,
+    #[serde(with = "crate::ospath")]
+    pub path: PathBuf,
+    pub layout: Layout,
+    /// captured pane contents by window and pane index, empty when not captured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scrollback: Vec<Vec<String>>,
+}
+
+fn get_path() -> Result<PathBuf> {
+    Ok(state::get_dir()?.join("snapshot.json"))
+}
+
+// pane contents are written here on restore for the panes to print
+fn get_scrollback_dir() -> Result<PathBuf> {
+    Ok(state::get_dir()?.join("scrollback"))
+}
+
+impl SessionSnapshot {
+    /// Records the layout of a running session and, if `scrollback` is
+    /// non-zero, up to that many lines of every pane
+    pub fn take(name: &str, path: &Path, scrollback: usize) -> Result<Self> {
+        let mut captured: Vec<Vec<String>> = vec![];
+        let layout = Layout::capture_with(name, path, |window_index, pane| {
+            if scrollback == 0 {
+                return Ok(());
+            }
+            if captured.len() <= window_index {
+                captured.resize(window_index + 1, vec![]);
+            }
+            captured[window_index].push(tmux::capture_pane(&pane.id, scrollback)?);
+            Ok(())
+        })?;
+
+        Ok(SessionSnapshot {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            layout,
+            scrollback: captured,
+        })
+    }
+
+    /// Recreates the session detached, captured pane contents are printed
+    /// back into their panes before the startup commands run and the files
+    /// they were printed from are removed
+    pub fn restore(&self) -> Result<()> {
+        let mut layout = self.layout.clone();
+        let dir = get_scrollback_dir()?;
+
+        for (window_index, window) in layout.windows.iter_mut().enumerate() {
+            for (pane_index, pane) in window.panes.iter_mut().enumerate() {
+                let Some(content) = self
+                    .scrollback
+                    .get(window_index)
+                    .and_then(|w| w.get(pane_index))
+                    .filter(|c| !c.is_empty())
+                else {
+                    continue;
+                };
+
+                fs::create_dir_all(&dir)?;
+                let file_name = format!(
+                    "{}-{}-{}.txt",
+                    self.name.replace('/', "_"),
+                    window_index,
+                    pane_index
+                );
+                let file = dir.join(file_name);
+                fs::write(&file, format!("{}\n", content))?;
+                let file = shell::quote(&file);
+                pane.commands
+                    .insert(0, format!("clear; cat -- {}; rm -f -- {}", file, file));
+            }
+        }
+
+        layout.create_session(&self.name, &self.path, &Default::default())
+    }
+}
+
+impl Snapshot {
+    pub fn new(sessions: Vec<SessionSnapshot>) -> Self {
+        Snapshot {
+            created: state::now(),
+            sessions,
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        Self::load_from(&get_path()?)
+    }
+
+    /// Reads a snapshot file saved somewhere else than the state directory
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("can't read snapshot: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("can't parse snapshot: {}", path.display()))
+    }
+
+    /// Replaces the previous snapshot, pane contents left over from restoring
+    /// it are removed
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = get_path()?;
+        let _lock = lock(&path)?;
+        write_atomic(&path, self)?;
+
+        match fs::remove_dir_all(get_scrollback_dir()?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err)?,
+            _ => {}
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,44 @@
+use std::cmp::Ordering;
+
+use crate::{config::Workspace, state::State};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    /// most often and most recently selected first
+    Frecency,
+    Name,
+    Path,
+    /// config order
+    Added,
+}
+
+impl Sort {
+    /// Names for --sort, in the order of `ALL`
+    pub const CHOICES: &[&str; 4] = &["frecency", "name", "path", "added"];
+    pub const ALL: [Sort; 4] = [Sort::Frecency, Sort::Name, Sort::Path, Sort::Added];
+}
+
+/// Orders workspaces, keeping their index in the config next to them
+pub fn sort_workspaces<'a>(
+    workspaces: &'a [Workspace],
+    sort: Sort,
+    state: &State,
+) -> Vec<(usize, &'a Workspace)> {
+    let mut entries: Vec<(usize, &Workspace)> = workspaces.iter().enumerate().collect();
+
+    match sort {
+        Sort::Added => {}
+        Sort::Name => entries.sort_by_key(|(_, ws)| ws.display_name().to_lowercase()),
+        Sort::Path => entries.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path)),
+        Sort::Frecency => {
+            let now = crate::state::now();
+            entries.sort_by(|(_, a), (_, b)| {
+                let a = state.frecency(&a.path, now);
+                let b = state.frecency(&b.path, now);
+                b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+            });
+        }
+    }
+
+    entries
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result, anyhow};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{lock, write_atomic};
+
+/// Data wsm records on its own, kept apart from the hand-edited config
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct State {
+    // manifest path -> sha256 of the manifest content the user allowed
+    #[serde(default, with = "crate::ospath::keys")]
+    trusted: BTreeMap<PathBuf, String>,
+    // workspace path -> how often and when it was last selected
+    #[serde(default, with = "crate::ospath::keys")]
+    usage: BTreeMap<PathBuf, Usage>,
+    // session names, most recently entered first
+    #[serde(default)]
+    history: Vec<String>,
+    // workspace path -> assigned session name, see session::SessionNames
+    #[serde(default, with = "crate::ospath::keys")]
+    sessions: BTreeMap<PathBuf, SessionName>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionName {
+    /// name the template produced, a template change reassigns the name
+    pub base: String,
+    /// base, with a suffix if another workspace already had it
+    pub name: String,
+}
+
+const HISTORY_LEN: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Usage {
+    count: u64,
+    /// unix timestamp in seconds
+    last_used: u64,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// $XDG_STATE_HOME/wsm or ~/.local/state/wsm
+pub fn get_dir() -> Result<PathBuf> {
+    let state_home = match std::env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::home_dir()
+            .ok_or(anyhow!("can't get home dir"))?
+            .join(".local")
+            .join("state"),
+    };
+
+    Ok(state_home.join("wsm"))
+}
+
+fn get_path() -> Result<PathBuf> {
+    Ok(get_dir()?.join("state.json"))
+}
+
+impl State {
+    pub fn load() -> Result<Self> {
+        Self::load_from(&get_path()?)
+    }
+
+    fn load_from(state_path: &Path) -> Result<Self> {
+        if !state_path.exists() {
+            return Ok(State::default());
+        }
+
+        let state_str = fs::read_to_string(state_path)?;
+        serde_json::from_str(&state_str)
+            .with_context(|| format!("can't parse state: {}", state_path.display()))
+    }
+
+    /// Load-modify-save cycle under the state lock, see `Config::modify`
+    pub fn modify<T>(f: impl FnOnce(&mut State) -> Result<T>) -> Result<T> {
+        let state_path = get_path()?;
+        let _lock = lock(&state_path)?;
+
+        let mut state = Self::load_from(&state_path)?;
+        let result = f(&mut state)?;
+        write_atomic(&state_path, &state)?;
+
+        Ok(result)
+    }
+
+    pub fn is_trusted(&self, manifest_path: &Path, hash: &str) -> bool {
+        self.trusted.get(manifest_path).is_some_and(|h| h == hash)
+    }
+
+    pub fn trust(&mut self, manifest_path: &Path, hash: &str) {
+        self.trusted
+            .insert(manifest_path.to_path_buf(), hash.to_string());
+    }
+
+    pub fn untrust(&mut self, manifest_path: &Path) -> bool {
+        self.trusted.remove(manifest_path).is_some()
+    }
+
+    pub fn record_use(&mut self, workspace_path: &Path) {
+        let usage = self.usage.entry(workspace_path.to_path_buf()).or_default();
+        usage.count += 1;
+        usage.last_used = now();
+    }
+
+    pub fn forget(&mut self, workspace_path: &Path) {
+        self.usage.remove(workspace_path);
+    }
+
+    pub fn get_history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Moves the session to the front of the switch history
+    pub fn push_history(&mut self, session_name: &str) {
+        self.history.retain(|name| name != session_name);
+        self.history.insert(0, session_name.to_string());
+        self.history.truncate(HISTORY_LEN);
+    }
+
+    pub fn get_session_names(&self) -> &BTreeMap<PathBuf, SessionName> {
+        &self.sessions
+    }
+
+    pub fn set_session_names(&mut self, sessions: BTreeMap<PathBuf, SessionName>) {
+        self.sessions = sessions;
+    }
+
+    /// Selection count weighted by how recently the workspace was last used,
+    /// 0 for workspaces that were never selected
+    pub fn frecency(&self, workspace_path: &Path, now: u64) -> f64 {
+        let Some(usage) = self.usage.get(workspace_path) else {
+            return 0.0;
+        };
+
+        let age = now.saturating_sub(usage.last_used);
+        let weight = match age {
+            0..3_600 => 4.0,
+            3_600..86_400 => 2.0,
+            86_400..604_800 => 0.5,
+            _ => 0.25,
+        };
+        usage.count as f64 * weight
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::{
+    color::{DIM, GREEN, RED, RESET, YELLOW},
+    tmux::{self, SessionInfo},
+};
+
+/// Visible width the marker column is padded to, e.g. "●12!#". Sessions with
+/// 100 or more windows make it wider.
+pub const WIDTH: usize = 5;
+
+/// Session state of all workspaces, loaded with a single tmux call
+pub struct Statuses {
+    sessions: HashMap<String, SessionInfo>,
+    current: Option<String>,
+}
+
+impl Statuses {
+    pub fn load() -> Result<Self> {
+        let sessions = tmux::list_session_infos()?
+            .into_iter()
+            .map(|session| (session.name.clone(), session))
+            .collect();
+        let current = tmux::current_session().filter(|_| tmux::is_in_tmux());
+
+        Ok(Statuses { sessions, current })
+    }
+
+    /// Marker column for the session, padded to WIDTH visible characters so
+    /// the names after it line up: ○ not running, ● running, ◉ attached by
+    /// another client, then the window count, ! when a window rang the bell
+    /// and # on activity
+    pub fn markers(&self, session_name: &str, color: bool) -> String {
+        let mut out = String::new();
+        let mut len = 0;
+        let mut push = |code: &str, text: &str| {
+            match color && !code.is_empty() {
+                true => out.push_str(&format!("{}{}{}", code, text, RESET)),
+                false => out.push_str(text),
+            }
+            len += text.chars().count();
+        };
+
+        match self.sessions.get(session_name) {
+            None => push(DIM, "○"),
+            Some(session) => {
+                // our own client does not count as elsewhere
+                let own = (self.current.as_deref() == Some(session_name)) as usize;
+                match session.attached > own {
+                    true => push(YELLOW, "◉"),
+                    false => push(GREEN, "●"),
+                }
+                push("", &session.windows.to_string());
+                if session.bell {
+                    push(RED, "!");
+                }
+                if session.activity {
+                    push(YELLOW, "#");
+                }
+            }
+        }
+
+        out.push_str(&" ".repeat(WIDTH.saturating_sub(len)));
+        out
+    }
+}
@@ -1,31 +1,179 @@
 use std::{
-    path::Path,
-    process::{Command, ExitStatus},
+    collections::BTreeMap,
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
 };
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 
-pub fn new_session(session_name: &str, session_path: &Path, attach: bool) -> Result<ExitStatus> {
+/// Creates the session detached, `attach_session` or `switch_client` enter it
+pub fn new_session(
+    session_name: &str,
+    session_path: &Path,
+    env: &BTreeMap<String, String>,
+) -> Result<ExitStatus> {
     let mut tmux_command = Command::new("tmux");
     tmux_command
         .arg("new-session")
-        .arg("-A")
+        .arg("-d")
         .arg("-s")
-        .arg(&session_name)
+        .arg(session_name)
         .arg("-c")
-        .arg(&session_path);
+        .arg(session_path);
 
-    let status = match attach {
-        false => tmux_command.arg("-d").status()?,
-        true => tmux_command.status()?,
-    };
+    for (key, val) in env {
+        tmux_command.arg("-e").arg(format!("{}={}", key, val));
+    }
 
+    Ok(tmux_command.status()?)
+}
+
+pub fn attach_session(session_name: &str) -> Result<ExitStatus> {
+    let status = Command::new("tmux")
+        .arg("attach-session")
+        .arg("-t")
+        .arg(format!("={}", session_name))
+        .status()?;
     Ok(status)
 }
 
+// creates a background window and returns its id for use as a target
+pub fn new_window(session_name: &str, window_name: Option<&str>, path: &Path) -> Result<String> {
+    let mut tmux_command = Command::new("tmux");
+    tmux_command
+        .arg("new-window")
+        .arg("-d")
+        .arg("-P")
+        .arg("-F")
+        .arg("#{window_id}")
+        .arg("-t")
+        .arg(format!("={}:", session_name))
+        .arg("-c")
+        .arg(path);
+
+    if let Some(name) = window_name {
+        tmux_command.arg("-n").arg(name);
+    }
+
+    let output = tmux_command.output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "can't create window in session {}: {}",
+            session_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// creates a pane next to the target and returns its id
+pub fn split_window(target: &str, path: &Path, horizontal: bool) -> Result<String> {
+    let output = Command::new("tmux")
+        .arg("split-window")
+        .arg("-d")
+        .arg(if horizontal { "-h" } else { "-v" })
+        .arg("-P")
+        .arg("-F")
+        .arg("#{pane_id}")
+        .arg("-t")
+        .arg(target)
+        .arg("-c")
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "can't split window {}: {}",
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn kill_session(session_name: &str) -> Result<()> {
+    run(&["kill-session", "-t", &format!("={}", session_name)])
+}
+
+pub fn rename_session(session_name: &str, new_name: &str) -> Result<()> {
+    run(&[
+        "rename-session",
+        "-t",
+        &format!("={}", session_name),
+        new_name,
+    ])
+}
+
+pub fn select_layout(target: &str, layout: &str) -> Result<()> {
+    run(&["select-layout", "-t", target, layout])
+}
+
+pub fn select_window(target: &str) -> Result<()> {
+    run(&["select-window", "-t", target])
+}
+
+pub fn rename_window(target: &str, name: &str) -> Result<()> {
+    run(&["rename-window", "-t", target, name])
+}
+
+// expands a tmux format for the target, e.g. #{window_id}
+pub fn display(target: &str, format: &str) -> Result<String> {
+    let output = Command::new("tmux")
+        .args(["display-message", "-p", "-t", target, format])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "can't get {} for {}: {}",
+            format,
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn run(args: &[&str]) -> Result<()> {
+    let output = Command::new("tmux").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tmux {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// types the command into the target pane and presses enter
+pub fn send_keys(target: &str, command: &str) -> Result<()> {
+    let status = Command::new("tmux")
+        .arg("send-keys")
+        .arg("-t")
+        .arg(target)
+        .arg("-l")
+        .arg(command)
+        .status()?;
+    if !status.success() {
+        return Err(anyhow!("can't send keys to {}", target));
+    }
+
+    Command::new("tmux")
+        .arg("send-keys")
+        .arg("-t")
+        .arg(target)
+        .arg("Enter")
+        .status()?;
+    Ok(())
+}
+
 pub fn has_session(session_name: &str) -> Result<bool> {
     let status = Command::new("tmux")
         .args(["has-session", "-t", format!("={}", &session_name).as_str()])
+        .stderr(Stdio::null())
         .status()?;
     Ok(status.success())
 }
@@ -39,16 +187,180 @@ pub fn switch_client(session_name: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn is_same_tmux_session(session_name: &str) -> bool {
-    let current_session = Command::new("tmux")
+pub fn current_session() -> Option<String> {
+    Command::new("tmux")
         .arg("display-message")
         .arg("-p")
         .arg("#S")
         .output()
         .ok()
-        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+pub fn is_same_tmux_session(session_name: &str) -> bool {
+    Some(session_name) == current_session().as_deref()
+}
+
+#[derive(Debug)]
+pub struct WindowInfo {
+    pub id: String,
+    pub index: String,
+    pub name: String,
+    /// tmux names the window after its running command
+    pub automatic_rename: bool,
+    pub layout: String,
+}
+
+#[derive(Debug)]
+pub struct PaneInfo {
+    pub id: String,
+    pub command: String,
+    pub path: PathBuf,
+}
+
+pub fn list_windows(session_name: &str) -> Result<Vec<WindowInfo>> {
+    let target = format!("={}", session_name);
+    let output = list(&[
+        "list-windows",
+        "-t",
+        &target,
+        "-F",
+        "#{window_id}\t#{window_index}\t#{window_layout}\t#{automatic-rename}\t#{window_name}",
+    ])?;
+
+    let windows = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            Some(WindowInfo {
+                id: fields.next()?.to_string(),
+                index: fields.next()?.to_string(),
+                layout: fields.next()?.to_string(),
+                automatic_rename: fields.next()? == "1",
+                name: fields.next()?.to_string(),
+            })
+        })
+        .collect();
+    Ok(windows)
+}
+
+pub fn list_panes(window_target: &str) -> Result<Vec<PaneInfo>> {
+    let output = list_raw(&[
+        "list-panes",
+        "-t",
+        window_target,
+        "-F",
+        "#{pane_id}\t#{pane_current_command}\t#{pane_current_path}",
+    ])?;
+
+    // the path is kept as raw bytes, it may not be UTF-8
+    let panes = output
+        .split(|b| *b == b'\n')
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, |b| *b == b'\t');
+            Some(PaneInfo {
+                id: String::from_utf8_lossy(fields.next()?).into_owned(),
+                command: String::from_utf8_lossy(fields.next()?).into_owned(),
+                path: PathBuf::from(OsStr::from_bytes(fields.next()?)),
+            })
+        })
+        .collect();
+    Ok(panes)
+}
+
+/// A running session with its state as shown by the picker markers
+pub struct SessionInfo {
+    pub name: String,
+    /// number of clients attached to the session
+    pub attached: usize,
+    pub windows: usize,
+    /// a window has a bell alert
+    pub bell: bool,
+    /// a window has an activity alert
+    pub activity: bool,
+}
+
+/// Every running session in one tmux call, empty when there is no server
+pub fn list_session_infos() -> Result<Vec<SessionInfo>> {
+    let output = Command::new("tmux")
+        .args([
+            "-u",
+            "list-sessions",
+            "-F",
+            "#{session_attached}\t#{session_windows}\t#{session_alerts}\t#{session_name}",
+        ])
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Ok(vec![]);
+    }
+
+    // session_alerts is a list like "1!,3#", ! for bell and # for activity
+    let sessions = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let attached = fields.next()?.parse().ok()?;
+            let windows = fields.next()?.parse().ok()?;
+            let alerts = fields.next()?;
+            Some(SessionInfo {
+                attached,
+                windows,
+                bell: alerts.contains('!'),
+                activity: alerts.contains('#'),
+                name: fields.next()?.to_string(),
+            })
+        })
+        .collect();
+    Ok(sessions)
+}
+
+// names of all sessions, empty when no tmux server is running
+pub fn list_sessions() -> Result<Vec<String>> {
+    Ok(list_session_infos()?
+        .into_iter()
+        .map(|session| session.name)
+        .collect())
+}
+
+// last `lines` lines of the pane history including the visible part
+pub fn capture_pane(pane_id: &str, lines: usize) -> Result<String> {
+    let start = format!("-{}", lines);
+    let output = list(&["capture-pane", "-p", "-J", "-t", pane_id, "-S", &start])?;
+    Ok(output.trim_end().to_string())
+}
+
+// visible lines start..=end of the target with colors kept
+pub fn capture_pane_colored(target: &str, start: i32, end: i32) -> Result<String> {
+    list(&[
+        "capture-pane",
+        "-p",
+        "-e",
+        "-t",
+        target,
+        "-S",
+        &start.to_string(),
+        "-E",
+        &end.to_string(),
+    ])
+}
+
+// -u: without a UTF-8 locale tmux replaces the tabs separating fields with '_'
+fn list(args: &[&str]) -> Result<String> {
+    Ok(String::from_utf8_lossy(&list_raw(args)?).into_owned())
+}
 
-    Some(session_name) == current_session.as_deref()
+fn list_raw(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("tmux").arg("-u").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tmux {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
 }
 
 pub fn is_in_tmux() -> bool {